@@ -121,7 +121,7 @@ pub fn render_camera(ecs: &World, ctx: &mut Rltk) {
     let mut data = (&positions, &renderables, !&hidden)
         .join()
         .collect::<Vec<_>>();
-    data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
+    data.sort_by_key(|&a| std::cmp::Reverse(a.1.render_order));
     for (pos, render, _hidden) in data.iter() {
         let idx = map.xy_idx(pos.x, pos.y);
         if map.visible_tiles[idx] && in_screen_bounds(ecs, pos.x, pos.y) {
@@ -157,6 +157,10 @@ fn get_tile_glyph(idx: usize, map: &Map) -> (rltk::FontCharType, RGB, RGB) {
             glyph = rltk::to_cp437('▼');
             fg = COLORS.dark_cyan;
         }
+        TileType::UpStairs => {
+            glyph = rltk::to_cp437('▲');
+            fg = COLORS.dark_cyan;
+        }
     }
     if !map.visible_tiles[idx] {
         fg = fg.to_greyscale();
@@ -253,10 +257,10 @@ mod tests {
         state.ecs.insert(Point::new(player_x, player_y));
         let (min_x, max_x, min_y, max_y) = get_screen_bounds(&state.ecs);
         // these are relative to BOUNDS.height/width
-        assert_eq!(min_x, -70);
-        assert_eq!(max_x, 110);
+        assert_eq!(min_x, 0);
+        assert_eq!(max_x, 40);
 
-        assert_eq!(min_y, -45);
-        assert_eq!(max_y, 75);
+        assert_eq!(min_y, -16);
+        assert_eq!(max_y, 46);
     }
 }
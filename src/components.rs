@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use crate::dice::Dice;
 use rltk::RGB;
 use serde::{Deserialize, Serialize};
 #[allow(deprecated)]
@@ -35,11 +36,73 @@ pub struct Viewshed {
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Monster {}
 
+// Chaser means: goes straight for its prey. Kiter means: keeps its distance and shoots.
+// Coward means: runs once badly hurt. Ambusher means: waits until its prey is close.
+// Wanderer means: roams until it spots prey. Guard means: won't stray far from home.
+// Its faction decides what counts as prey.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum BehaviourKind {
+    Chaser,
+    Kiter,
+    Coward,
+    Ambusher,
+    Wanderer,
+    Guard,
+}
+
+impl BehaviourKind {
+    pub fn from_name(name: &str) -> Option<BehaviourKind> {
+        match name {
+            "chaser" => Some(BehaviourKind::Chaser),
+            "kiter" => Some(BehaviourKind::Kiter),
+            "coward" => Some(BehaviourKind::Coward),
+            "ambusher" => Some(BehaviourKind::Ambusher),
+            "wanderer" => Some(BehaviourKind::Wanderer),
+            "guard" => Some(BehaviourKind::Guard),
+            _ => None,
+        }
+    }
+}
+
+// Idle means: hasn't noticed any prey. Hunting means: going for the prey it can see.
+// Searching means: heading for where it last saw its prey. Fleeing means: getting away.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum AiState {
+    Idle,
+    Hunting,
+    Searching,
+    Fleeing,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Behaviour {
+    pub kind: BehaviourKind,
+    pub state: AiState,
+    pub last_seen: Option<rltk::Point>,
+    pub home: rltk::Point,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct RangedAttack {
+    pub range: i32,
+}
+
+// A spell a monster can cast. Unlike the player's it has no hotkey, and stays on the level.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct KnownSpell {
+    pub owner: Entity,
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct Name {
     pub name: String,
 }
 
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Description {
+    pub text: String,
+}
+
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct BlocksTile {}
 
@@ -54,9 +117,16 @@ pub struct EntityStats {
     // As opposed to stats for a run or w.e
     pub defense: i32,
     pub power: i32,
+    pub damage: Dice,
+    pub level: i32,
     pub pools: HashMap<String, Pool>,
 }
 
+/// XP needed to advance past the given level
+pub fn experience_for_level(level: i32) -> i32 {
+    level * 20
+}
+
 impl EntityStats {
     pub fn get(&self, stat: &str) -> (i32, i32) {
         match self.pools.get(stat) {
@@ -65,24 +135,39 @@ impl EntityStats {
         }
     }
     pub fn set_current(&mut self, key: &str, value: i32) {
-        let mut pool = self.pools.get_mut(key).unwrap();
+        let pool = self.pools.get_mut(key).unwrap();
         pool.current = value;
     }
 
     pub fn set_max(&mut self, key: &str, value: i32) {
-        let mut pool = self.pools.get_mut(key).unwrap();
+        let pool = self.pools.get_mut(key).unwrap();
         pool.max = value;
     }
 
     pub fn deplete(&mut self, key: &str, value: i32) {
-        let mut pool = self.pools.get_mut(key).unwrap();
+        let pool = self.pools.get_mut(key).unwrap();
         pool.current -= value;
     }
 
     pub fn restore(&mut self, key: &str, value: i32) {
-        let mut pool = self.pools.get_mut(key).unwrap();
+        let pool = self.pools.get_mut(key).unwrap();
         pool.current = std::cmp::min(pool.current + value, pool.max);
     }
+
+    /// Adds XP to the "xp" pool, returning how many levels were gained
+    pub fn gain_experience(&mut self, amount: i32) -> i32 {
+        let mut levels = 0;
+        if let Some(pool) = self.pools.get_mut("xp") {
+            pool.current += amount;
+            while pool.current >= pool.max {
+                pool.current -= pool.max;
+                self.level += 1;
+                pool.max = experience_for_level(self.level);
+                levels += 1;
+            }
+        }
+        levels
+    }
 }
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
@@ -90,6 +175,30 @@ pub struct WantsToMelee {
     pub target: Entity,
 }
 
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToShoot {
+    pub target: Entity,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct GrantsExperience {
+    pub amount: i32,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct PendingLevelUp {
+    pub levels: i32,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Initiative {
+    pub energy: i32,
+    pub speed: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MyTurn {}
+
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Item {}
 
@@ -107,22 +216,22 @@ pub struct Ranged {
     pub range: i32,
 }
 
+// Calls up a mob from the raws to fight for the player, for so many turns
 #[derive(Component, Debug, ConvertSaveload, Clone)]
-pub struct InflictsDamage {
-    pub damage: i32,
+pub struct Summons {
+    pub mob: String,
+    pub duration: i32,
 }
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
-pub struct AreaOfEffect {
-    pub radius: i32,
+pub struct InflictsDamage {
+    pub damage: Dice,
 }
 
-/*
 #[derive(Component, Debug, ConvertSaveload, Clone)]
-pub struct Confusion {
-    pub turns : i32
+pub struct AreaOfEffect {
+    pub radius: i32,
 }
-*/
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct ProvidesHealing {
@@ -161,26 +270,139 @@ pub struct WantsToDropItem {
     pub item: Entity,
 }
 
+#[derive(Component, Debug, ConvertSaveload)]
+pub struct WantsToThrowItem {
+    pub item: Entity,
+    pub target: rltk::Point,
+}
+
 pub struct SerializeMe;
 
 // Special component that exists to help serialize the game data
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct SerializationHelper {
     pub map: super::map::Map,
+    pub log: super::ActionLog,
+    pub turns: super::systems::initiative::TurnCounter,
+    pub dungeon: super::dungeon::MasterDungeonMap,
 }
 
 // Status system. Each status works in a predicable way.
 // Bleed X means: take x dmg. X decreases every turn.
-// there are a bunch of different types of statuses. how to model this in ECS-land?
+// Burn/Poison X means: take x dmg every turn until the duration runs out.
+// Slow/Haste means: act at half/double speed. Stun means: lose every turn.
+// Confusion means: movement goes in a random direction.
+// Charm means: fights on the player's side. Summoned means: vanishes once it runs out.
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum StatusType {
+    Bleed,
+    Burn,
+    Poison,
+    Slow,
+    Haste,
+    Stun,
+    Confusion,
+    Charm,
+    Summoned,
+}
+
+impl StatusType {
+    pub fn from_name(name: &str) -> Option<StatusType> {
+        match name {
+            "bleed" => Some(StatusType::Bleed),
+            "burn" => Some(StatusType::Burn),
+            "poison" => Some(StatusType::Poison),
+            "slow" => Some(StatusType::Slow),
+            "haste" => Some(StatusType::Haste),
+            "stun" => Some(StatusType::Stun),
+            "confusion" => Some(StatusType::Confusion),
+            "charm" => Some(StatusType::Charm),
+            "summoned" => Some(StatusType::Summoned),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusType::Bleed => "Bleed",
+            StatusType::Burn => "Burn",
+            StatusType::Poison => "Poison",
+            StatusType::Slow => "Slow",
+            StatusType::Haste => "Haste",
+            StatusType::Stun => "Stun",
+            StatusType::Confusion => "Confusion",
+            StatusType::Charm => "Charm",
+            StatusType::Summoned => "Summoned",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Status {
+    pub typ: StatusType,
+    pub magnitude: i32,
     pub duration: i32,
-    pub typ: str,
 }
 
 impl Status {
-    fn _tick() {
-        //
+    /// Per-turn damage dealt by this status, if any
+    pub fn damage(&self) -> i32 {
+        match self.typ {
+            StatusType::Bleed | StatusType::Burn | StatusType::Poison => self.magnitude,
+            _ => 0,
+        }
     }
+
+    /// Advance the status by one turn. Returns false once it has worn off
+    pub fn tick(&mut self) -> bool {
+        self.duration -= 1;
+        if self.typ == StatusType::Bleed {
+            self.magnitude -= 1;
+        }
+        self.duration > 0 && self.magnitude > 0
+    }
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct StatusEffects {
+    pub statuses: Vec<Status>,
+}
+
+impl StatusEffects {
+    /// Statuses of the same type stack: magnitudes add, the longer duration wins
+    pub fn add(&mut self, status: Status) {
+        match self.statuses.iter_mut().find(|s| s.typ == status.typ) {
+            Some(existing) => {
+                existing.magnitude += status.magnitude;
+                existing.duration = i32::max(existing.duration, status.duration);
+            }
+            None => self.statuses.push(status),
+        }
+    }
+
+    pub fn remove(&mut self, typ: StatusType) {
+        self.statuses.retain(|s| s.typ != typ);
+    }
+
+    pub fn has(&self, typ: StatusType) -> bool {
+        self.statuses.iter().any(|s| s.typ == typ)
+    }
+
+    pub fn skips_turn(&self) -> bool {
+        self.has(StatusType::Stun)
+    }
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct InflictsStatus {
+    pub typ: StatusType,
+    pub magnitude: i32,
+    pub duration: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct CuresStatus {
+    pub typ: StatusType,
 }
 
 #[derive(Component, ConvertSaveload, Clone)]
@@ -193,9 +415,14 @@ pub struct Spell {
     pub hotkey: String,
 }
 
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MagicSchools {
+    pub names: Vec<String>,
+}
+
 #[derive(Component, Debug, ConvertSaveload)]
 pub struct WantsToCastSpell {
-    pub source: Entity,
+    pub spell: Entity,
     pub target: Option<rltk::Point>,
 }
 
@@ -204,18 +431,64 @@ pub struct ParticleLifetime {
     pub lifetime_ms: f32,
 }
 
-#[derive(Component, Serialize, Deserialize, Clone)]
-pub struct Antagonistic {}
+// Who something sides with. How factions treat each other lives in the raws.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Faction {
+    pub name: String,
+}
+
+impl Faction {
+    /// The side it's fighting for right now, which charm can change for a while
+    pub fn allegiance(&self, effects: Option<&StatusEffects>) -> &str {
+        match effects {
+            Some(effects) if effects.has(StatusType::Charm) => crate::raws::ALLY_FACTION,
+            _ => &self.name,
+        }
+    }
+}
+
+// Things a creature says when the player bumps into it
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Quips {
+    pub lines: Vec<String>,
+}
 
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Hidden {}
 
+// Belongs to whatever level it was put down on, and stays behind when the player leaves it
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct OnLevel {}
+
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct ApplyTeleport {
     pub dest_x: i32,
     pub dest_y: i32,
 }
 
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Pushes {
+    pub distance: i32,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Pulls {
+    pub distance: i32,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct KnocksBack {
+    pub distance: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct SwapsPlaces {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ApplyMove {
+    pub dest_idx: usize,
+}
+
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct TeleportTo {
     pub x: i32,
@@ -84,6 +84,11 @@ pub struct Config {
     pub exit: String,
     pub select: String,
     pub wait: String,
+    pub inventory: String,
+    pub drop: String,
+    pub throw: String,
+    pub inspect: String,
+    pub sort: String,
     pub hk1: String,
     pub hk2: String,
     pub hk3: String,
@@ -96,6 +101,23 @@ pub struct Config {
     pub hk10: String,
 }
 
+impl Config {
+    pub fn hotkeys(&self) -> Vec<String> {
+        vec![
+            self.hk1.clone(),
+            self.hk2.clone(),
+            self.hk3.clone(),
+            self.hk4.clone(),
+            self.hk5.clone(),
+            self.hk6.clone(),
+            self.hk7.clone(),
+            self.hk8.clone(),
+            self.hk9.clone(),
+            self.hk10.clone(),
+        ]
+    }
+}
+
 pub struct Input {
     pub left: VirtualKeyCode,
     pub down: VirtualKeyCode,
@@ -105,6 +127,11 @@ pub struct Input {
     pub exit: VirtualKeyCode,
     pub select: VirtualKeyCode,
     pub wait: VirtualKeyCode,
+    pub inventory: VirtualKeyCode,
+    pub drop: VirtualKeyCode,
+    pub throw: VirtualKeyCode,
+    pub inspect: VirtualKeyCode,
+    pub sort: VirtualKeyCode,
     pub hk1: VirtualKeyCode,
     pub hk2: VirtualKeyCode,
     pub hk3: VirtualKeyCode,
@@ -117,6 +144,15 @@ pub struct Input {
     pub hk10: VirtualKeyCode,
 }
 
+impl Input {
+    pub fn hotkeys(&self) -> Vec<VirtualKeyCode> {
+        vec![
+            self.hk1, self.hk2, self.hk3, self.hk4, self.hk5, self.hk6, self.hk7, self.hk8,
+            self.hk9, self.hk10,
+        ]
+    }
+}
+
 lazy_static! {
     pub static ref CONFIG: Config = get_config();
     pub static ref INPUT: Input = Input {
@@ -128,6 +164,11 @@ lazy_static! {
         exit: VirtualKeyCode::from_str(CONFIG.exit.as_str()),
         select: VirtualKeyCode::from_str(CONFIG.select.as_str()),
         wait: VirtualKeyCode::from_str(CONFIG.wait.as_str()),
+        inventory: VirtualKeyCode::from_str(CONFIG.inventory.as_str()),
+        drop: VirtualKeyCode::from_str(CONFIG.drop.as_str()),
+        throw: VirtualKeyCode::from_str(CONFIG.throw.as_str()),
+        inspect: VirtualKeyCode::from_str(CONFIG.inspect.as_str()),
+        sort: VirtualKeyCode::from_str(CONFIG.sort.as_str()),
         hk1: VirtualKeyCode::from_str(CONFIG.hk1.as_str()),
         hk2: VirtualKeyCode::from_str(CONFIG.hk2.as_str()),
         hk3: VirtualKeyCode::from_str(CONFIG.hk3.as_str()),
@@ -0,0 +1,211 @@
+// Text about an entity for the player to read: whatever flavour the raws gave it, and a summary
+// of what it does. The summary is worked out from its components, so it can't fall behind the
+// raws.
+
+use crate::{
+    map::Map, AreaOfEffect, Consumable, CostsMana, CuresStatus, Description, EntityStats, Hidden,
+    InflictsDamage, InflictsStatus, KnocksBack, Name, Position, ProvidesHealing, ProvidesMana,
+    Pulls, Pushes, Ranged, Renderable, StatusType, Summons, SwapsPlaces, TeleportTo, TileType,
+};
+use rltk::Point;
+use specs::prelude::*;
+
+/// Something the player can see on a tile they're looking at
+pub struct Sighting {
+    pub name: String,
+    pub health: Option<(i32, i32)>,
+    pub flavour: Option<String>,
+}
+
+pub fn flavour(ecs: &World, entity: Entity) -> Option<String> {
+    ecs.read_storage::<Description>()
+        .get(entity)
+        .map(|description| description.text.clone())
+}
+
+/// What an item or spell does, one short line per effect
+pub fn mechanics(ecs: &World, entity: Entity) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    if let Some(ranged) = ecs.read_storage::<Ranged>().get(entity) {
+        lines.push(format!("Range {}", ranged.range));
+    }
+    if let Some(aoe) = ecs.read_storage::<AreaOfEffect>().get(entity) {
+        lines.push(format!("Radius {}", aoe.radius));
+    }
+    if let Some(damage) = ecs.read_storage::<InflictsDamage>().get(entity) {
+        lines.push(format!("{} damage", damage.damage));
+    }
+    if let Some(status) = ecs.read_storage::<InflictsStatus>().get(entity) {
+        // Neither of these comes in degrees, so there's no magnitude to show
+        lines.push(match status.typ {
+            StatusType::Charm => format!("Charms for {} turns", status.duration),
+            StatusType::Summoned => format!("Fades away after {} turns", status.duration),
+            _ => format!(
+                "{} {} for {} turns",
+                status.typ.name(),
+                status.magnitude,
+                status.duration
+            ),
+        });
+    }
+    if let Some(summons) = ecs.read_storage::<Summons>().get(entity) {
+        lines.push(format!(
+            "Summons {} for {} turns",
+            summons.mob, summons.duration
+        ));
+    }
+    if let Some(heal) = ecs.read_storage::<ProvidesHealing>().get(entity) {
+        lines.push(format!("Heals {}", heal.heal_amount));
+    }
+    if let Some(mana) = ecs.read_storage::<ProvidesMana>().get(entity) {
+        lines.push(format!("Restores {} mana", mana.mana_amount));
+    }
+    if let Some(cure) = ecs.read_storage::<CuresStatus>().get(entity) {
+        lines.push(format!("Cures {}", cure.typ.name()));
+    }
+    if let Some(push) = ecs.read_storage::<Pushes>().get(entity) {
+        lines.push(format!("Pushes {}", push.distance));
+    }
+    if let Some(pull) = ecs.read_storage::<Pulls>().get(entity) {
+        lines.push(format!("Pulls {}", pull.distance));
+    }
+    if let Some(knockback) = ecs.read_storage::<KnocksBack>().get(entity) {
+        lines.push(format!("Knocks back {}", knockback.distance));
+    }
+    if ecs.read_storage::<SwapsPlaces>().get(entity).is_some() {
+        lines.push("Swaps places with the target".to_string());
+    }
+    if ecs.read_storage::<TeleportTo>().get(entity).is_some() {
+        lines.push("Teleports you to the chosen tile".to_string());
+    }
+    if let Some(cost) = ecs.read_storage::<CostsMana>().get(entity) {
+        lines.push(format!("Costs {} mana", cost.mana_amount));
+    }
+    if ecs.read_storage::<Consumable>().get(entity).is_some() {
+        lines.push("Single use".to_string());
+    }
+    lines
+}
+
+/// What the ground is like at a tile, or None if the player has never seen it
+pub fn terrain(ecs: &World, tile: Point) -> Option<String> {
+    let map = ecs.fetch::<Map>();
+    if tile.x < 0 || tile.x >= map.width || tile.y < 0 || tile.y >= map.height {
+        return None;
+    }
+    let idx = map.xy_idx(tile.x, tile.y);
+    if !map.revealed_tiles[idx] {
+        return None;
+    }
+    let ground = match map.tiles[idx] {
+        TileType::Floor => "Floor",
+        TileType::Wall => "Wall",
+        TileType::DownStairs => "Stairs down",
+        TileType::UpStairs => "Stairs up",
+    };
+    if map.bloodstains.contains(&idx) {
+        return Some(format!("{}, stained with blood", ground));
+    }
+    Some(ground.to_string())
+}
+
+/// Everything the player can currently see at a tile, topmost first
+pub fn sightings(ecs: &World, tile: Point) -> Vec<Sighting> {
+    {
+        let map = ecs.fetch::<Map>();
+        if tile.x < 0 || tile.x >= map.width || tile.y < 0 || tile.y >= map.height {
+            return Vec::new();
+        }
+        if !map.visible_tiles[map.xy_idx(tile.x, tile.y)] {
+            return Vec::new();
+        }
+    }
+    let entities = ecs.entities();
+    let positions = ecs.read_storage::<Position>();
+    let names = ecs.read_storage::<Name>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let stats = ecs.read_storage::<EntityStats>();
+
+    let mut seen = (&entities, &positions, &names, &renderables, !&hidden)
+        .join()
+        .filter(|(_, pos, ..)| pos.x == tile.x && pos.y == tile.y)
+        .map(|(entity, _, name, render, _)| (render.render_order, entity, name))
+        .collect::<Vec<_>>();
+    seen.sort_by_key(|(order, ..)| *order);
+    seen.into_iter()
+        .map(|(_, entity, name)| Sighting {
+            name: name.name.clone(),
+            health: stats.get(entity).map(|stats| stats.get("hit_points")),
+            flavour: flavour(ecs, entity),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::Simulation;
+
+    #[test]
+    fn test_scroll_mechanics() {
+        let mut sim = Simulation::new(18, ["Pyromancy", "Cryomancy"]);
+        let scroll = sim.spawn("Fireball Scroll", 1, 1);
+        assert_eq!(
+            mechanics(&sim.state.ecs, scroll),
+            vec![
+                "Range 6",
+                "Radius 3",
+                "6d6 damage",
+                "Burn 2 for 3 turns",
+                "Costs 2 mana",
+                "Single use"
+            ]
+        );
+        assert!(flavour(&sim.state.ecs, scroll).is_some());
+    }
+
+    #[test]
+    fn test_spell_mechanics() {
+        let mut sim = Simulation::new(18, ["Conjuration", "Translocation"]);
+        let ecs = &sim.state.ecs;
+        let summon = sim.named("Summon Wolf").unwrap();
+        assert_eq!(
+            mechanics(ecs, summon),
+            vec!["Range 4", "Summons Wolf for 20 turns", "Costs 3 mana"]
+        );
+        let blink = sim.named("Blink").unwrap();
+        assert!(mechanics(ecs, blink).contains(&"Teleports you to the chosen tile".to_string()));
+
+        let charm = sim
+            .ecs()
+            .create_entity()
+            .with(InflictsStatus {
+                typ: StatusType::Charm,
+                magnitude: 1,
+                duration: 8,
+            })
+            .build();
+        assert_eq!(mechanics(&sim.state.ecs, charm), vec!["Charms for 8 turns"]);
+    }
+
+    #[test]
+    fn test_sightings() {
+        let mut sim = Simulation::new(18, ["Pyromancy", "Cryomancy"]);
+        let pos = sim.player_pos();
+        sim.spawn("Goblin", pos.x, pos.y);
+        sim.state.run_systems();
+        let ecs = &sim.state.ecs;
+
+        let seen = sightings(ecs, pos);
+        let goblin = seen.iter().find(|s| s.name == "Goblin").unwrap();
+        assert!(goblin.health.is_some());
+        assert!(goblin.flavour.is_some());
+        assert!(terrain(ecs, pos).is_some());
+
+        let unseen = Point::new(-1, -1);
+        assert!(sightings(ecs, unseen).is_empty());
+        assert!(terrain(ecs, unseen).is_none());
+    }
+}
@@ -0,0 +1,167 @@
+use rltk::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// An amount decided by dice, like "2d6+1". A plain number is a roll of no dice at all.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Dice {
+    pub count: i32,
+    pub sides: i32,
+    pub bonus: i32,
+}
+
+impl Dice {
+    pub fn new(count: i32, sides: i32, bonus: i32) -> Dice {
+        Dice {
+            count,
+            sides,
+            bonus,
+        }
+    }
+
+    pub fn flat(amount: i32) -> Dice {
+        Dice::new(0, 0, amount)
+    }
+
+    /// Reads "NdS", "NdS+B", "NdS-B" or a bare number
+    pub fn parse(text: &str) -> Option<Dice> {
+        let text = text.trim();
+        let (count, rest) = match text.split_once('d') {
+            Some(split) => split,
+            None => return text.parse().ok().map(Dice::flat),
+        };
+        let (sides, bonus) = match rest.find(['+', '-']) {
+            Some(at) => (
+                &rest[..at],
+                rest[at..].trim_start_matches('+').parse().ok()?,
+            ),
+            None => (rest, 0),
+        };
+        let count = match count {
+            "" => 1,
+            count => count.parse().ok()?,
+        };
+        let sides = sides.parse().ok()?;
+        match count >= 0 && sides > 0 {
+            true => Some(Dice::new(count, sides, bonus)),
+            false => None,
+        }
+    }
+
+    pub fn roll(&self, rng: &mut RandomNumberGenerator) -> i32 {
+        self.roll_times(rng, self.count)
+    }
+
+    /// A critical hit rolls twice the dice, but only adds the bonus once
+    pub fn roll_critical(&self, rng: &mut RandomNumberGenerator) -> i32 {
+        self.roll_times(rng, self.count * 2)
+    }
+
+    fn roll_times(&self, rng: &mut RandomNumberGenerator, count: i32) -> i32 {
+        let rolled = match count > 0 && self.sides > 0 {
+            true => rng.roll_dice(count, self.sides),
+            false => 0,
+        };
+        i32::max(0, rolled + self.bonus)
+    }
+}
+
+impl fmt::Display for Dice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.count == 0 {
+            return write!(f, "{}", self.bonus);
+        }
+        write!(f, "{}d{}", self.count, self.sides)?;
+        match self.bonus {
+            0 => Ok(()),
+            bonus if bonus > 0 => write!(f, "+{}", bonus),
+            bonus => write!(f, "{}", bonus),
+        }
+    }
+}
+
+/// Attack rolls are a d20 plus the attacker's power, against ten plus the target's defense.
+/// A natural 20 always lands, and hard. A natural 1 always misses.
+pub const TO_HIT_DIE: i32 = 20;
+const BASE_DEFENSE: i32 = 10;
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Outcome {
+    Fumble,
+    Miss,
+    Hit,
+    Critical,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct AttackRoll {
+    pub natural: i32,
+    pub total: i32,
+    pub needed: i32,
+    pub outcome: Outcome,
+}
+
+pub fn attack_roll(rng: &mut RandomNumberGenerator, power: i32, defense: i32) -> AttackRoll {
+    judge_attack(rng.roll_dice(1, TO_HIT_DIE), power, defense)
+}
+
+fn judge_attack(natural: i32, power: i32, defense: i32) -> AttackRoll {
+    let total = natural + power;
+    let needed = BASE_DEFENSE + defense;
+    let outcome = match natural {
+        1 => Outcome::Fumble,
+        TO_HIT_DIE => Outcome::Critical,
+        _ if total >= needed => Outcome::Hit,
+        _ => Outcome::Miss,
+    };
+    AttackRoll {
+        natural,
+        total,
+        needed,
+        outcome,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        for (text, dice) in [
+            ("1d6", Dice::new(1, 6, 0)),
+            ("2d4+3", Dice::new(2, 4, 3)),
+            ("1d8-1", Dice::new(1, 8, -1)),
+            ("d20", Dice::new(1, 20, 0)),
+            ("20", Dice::flat(20)),
+        ] {
+            assert_eq!(Dice::parse(text), Some(dice));
+        }
+        assert_eq!(Dice::parse("d20").unwrap().to_string(), "1d20");
+        assert_eq!(Dice::parse("1d8-1").unwrap().to_string(), "1d8-1");
+        assert_eq!(Dice::flat(5).to_string(), "5");
+        for junk in ["", "d", "2d", "1d0", "xd6", "1d6+", "1d6+x"] {
+            assert_eq!(Dice::parse(junk), None, "{}", junk);
+        }
+    }
+
+    #[test]
+    fn test_rolls_stay_in_range() {
+        let mut rng = RandomNumberGenerator::seeded(7);
+        let dice = Dice::new(2, 6, 1);
+        for _ in 0..200 {
+            assert!((3..=13).contains(&dice.roll(&mut rng)));
+            assert!((5..=25).contains(&dice.roll_critical(&mut rng)));
+        }
+        assert_eq!(Dice::flat(4).roll_critical(&mut rng), 4);
+        assert_eq!(Dice::new(1, 4, -9).roll(&mut rng), 0);
+    }
+
+    #[test]
+    fn test_naturals_beat_the_odds() {
+        assert_eq!(judge_attack(1, 30, 0).outcome, Outcome::Fumble);
+        assert_eq!(judge_attack(20, 0, 30).outcome, Outcome::Critical);
+        assert_eq!(judge_attack(8, 3, 1).outcome, Outcome::Hit);
+        assert_eq!(judge_attack(7, 3, 1).outcome, Outcome::Miss);
+    }
+}
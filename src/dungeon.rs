@@ -0,0 +1,71 @@
+use crate::map::Map;
+use crate::systems::save_load::{self, SaveError};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value};
+use specs::prelude::*;
+use std::collections::BTreeMap;
+
+/// A level the player has left: its map, and everything that was on it serialized the same way
+/// a save is
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StoredLevel {
+    pub map: Map,
+    pub entities: JsonMap<String, Value>,
+}
+
+/// Every level the player has visited and then left, by depth
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct MasterDungeonMap {
+    levels: BTreeMap<i32, StoredLevel>,
+}
+
+impl MasterDungeonMap {
+    pub fn store_level(&mut self, level: StoredLevel) {
+        self.levels.insert(level.map.depth, level);
+    }
+
+    pub fn take_level(&mut self, depth: i32) -> Option<StoredLevel> {
+        self.levels.remove(&depth)
+    }
+
+    /// The highest save marker any stored entity holds
+    pub fn max_marker_id(&self) -> Option<u64> {
+        self.levels
+            .values()
+            .flat_map(|level| level.entities.values())
+            .filter_map(Value::as_array)
+            .flatten()
+            .filter_map(|entry| entry["marker"][0].as_u64())
+            .max()
+    }
+}
+
+/// Packs the current level and the given entities away in the dungeon master, then removes
+/// them from the world. They're removed even if they can't be stored.
+pub fn stash_level(ecs: &mut World, level_entities: &[Entity]) -> Result<(), SaveError> {
+    let entities = save_load::serialize_entities(ecs, level_entities);
+    ecs.delete_entities(level_entities)
+        .expect("Unable to delete entity");
+    let map = (*ecs.fetch::<Map>()).clone();
+    ecs.write_resource::<MasterDungeonMap>()
+        .store_level(StoredLevel {
+            map,
+            entities: entities?,
+        });
+    Ok(())
+}
+
+/// Brings a stored level back, returning false if the player has never left one at that depth
+pub fn restore_level(ecs: &mut World, depth: i32) -> Result<bool, SaveError> {
+    let level = match ecs.write_resource::<MasterDungeonMap>().take_level(depth) {
+        Some(level) => level,
+        None => return Ok(false),
+    };
+    {
+        let mut map = ecs.write_resource::<Map>();
+        *map = level.map;
+        map.tile_content = vec![Vec::new(); map.tile_count];
+    }
+    save_load::deserialize_components(ecs, level.entities)?;
+    Ok(true)
+}
@@ -1,13 +1,58 @@
 use super::*;
-use crate::{components::EntityStats, COLORS};
+use crate::systems::melee_combat::{describe_attack, roll_attack};
+use crate::{components::EntityStats, gamelog, GrantsExperience, Name, PendingLevelUp, COLORS};
+use rltk::RandomNumberGenerator;
 use specs::prelude::*;
 
+/// Spells and scrolls roll to hit the way blows do, on the caster's power, but with their own
+/// dice for damage
+pub fn attack(ecs: &mut World, attack: &EffectSpawner, target: Entity) {
+    let (damage, source) = match &attack.effect_type {
+        EffectType::Attack { damage, source } => (damage, source),
+        _ => return,
+    };
+    let entity_stats = ecs.read_storage::<EntityStats>();
+    let target_stats = match entity_stats.get(target) {
+        Some(stats) if stats.get("hit_points").0 > 0 => stats,
+        _ => return,
+    };
+    let power = attack
+        .creator
+        .and_then(|creator| entity_stats.get(creator))
+        .map_or(0, |stats| stats.power);
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    let (roll, amount) = roll_attack(&mut rng, power, target_stats.defense, damage);
+
+    let names = ecs.read_storage::<Name>();
+    let attacker = match attack.creator.and_then(|creator| names.get(creator)) {
+        Some(caster) => format!("{}'s {}", caster.name, source),
+        None => source.clone(),
+    };
+    let target_name = names
+        .get(target)
+        .map_or("something", |name| name.name.as_str());
+    let line = describe_attack(&attacker, "hits", target_name, &roll, amount);
+    ecs.fetch_mut::<gamelog::GameLog>().entries.push(line);
+    if amount > 0 {
+        add_effect(
+            ecs,
+            attack.creator,
+            EffectType::Damage { amount },
+            Targets::Single { target },
+        );
+    }
+}
+
 pub fn inflict_damage(ecs: &mut World, damage: &EffectSpawner, target: Entity) {
+    let mut killed = false;
     let mut entity_stats = ecs.write_storage::<EntityStats>();
     if let Some(pool) = entity_stats.get_mut(target) {
         if let EffectType::Damage { amount } = damage.effect_type {
+            let was_alive = pool.get("hit_points").0 > 0;
             pool.deplete("hit_points", amount);
+            killed = was_alive && pool.get("hit_points").0 < 1;
             add_effect(
+                ecs,
                 None,
                 EffectType::Particle {
                     glyph: rltk::to_cp437('‼'),
@@ -19,6 +64,43 @@ pub fn inflict_damage(ecs: &mut World, damage: &EffectSpawner, target: Entity) {
             );
         }
     }
+    std::mem::drop(entity_stats);
+
+    if killed {
+        if let Some(killer) = damage.creator {
+            award_experience(ecs, killer, target);
+        }
+    }
+}
+
+fn award_experience(ecs: &mut World, killer: Entity, victim: Entity) {
+    let amount = match ecs.read_storage::<GrantsExperience>().get(victim) {
+        Some(grants) => grants.amount,
+        None => return,
+    };
+
+    let mut entity_stats = ecs.write_storage::<EntityStats>();
+    if let Some(stats) = entity_stats.get_mut(killer) {
+        let levels = stats.gain_experience(amount);
+
+        if killer == *ecs.fetch::<Entity>() {
+            let mut gamelog = ecs.fetch_mut::<gamelog::GameLog>();
+            gamelog
+                .entries
+                .push(format!("You gain {} experience.", amount));
+        }
+
+        if levels > 0 {
+            let mut pending = ecs.write_storage::<PendingLevelUp>();
+            if let Some(pending) = pending.get_mut(killer) {
+                pending.levels += levels;
+            } else {
+                pending
+                    .insert(killer, PendingLevelUp { levels })
+                    .expect("Unable to insert level up");
+            }
+        }
+    }
 }
 
 pub fn heal_damage(ecs: &mut World, damage: &EffectSpawner, target: Entity) {
@@ -28,6 +110,7 @@ pub fn heal_damage(ecs: &mut World, damage: &EffectSpawner, target: Entity) {
         if let EffectType::Healing { amount } = damage.effect_type {
             pool.restore("hit_points", amount);
             add_effect(
+                ecs,
                 None,
                 EffectType::Particle {
                     glyph: rltk::to_cp437('♥'),
@@ -0,0 +1,302 @@
+use super::*;
+use crate::{
+    gamelog, map::TileType, ApplyTeleport, BlocksTile, Name, Player, Position, SpawnParticleLine,
+    StatusType, COLORS,
+};
+use rltk::Point;
+use specs::prelude::*;
+
+// Damage dealt per tile of movement left when a target is slammed into something
+const COLLISION_DAMAGE: i32 = 2;
+
+enum Obstacle {
+    Wall,
+    Entity(Entity),
+}
+
+fn position_of(ecs: &World, entity: Entity) -> Option<Point> {
+    ecs.read_storage::<Position>()
+        .get(entity)
+        .map(|pos| Point::new(pos.x, pos.y))
+}
+
+fn name_of(ecs: &World, entity: Entity) -> String {
+    match ecs.read_storage::<Name>().get(entity) {
+        Some(name) => name.name.clone(),
+        None => "something".to_string(),
+    }
+}
+
+// The tiles a target travels over, in order. Pushes continue along the line from the caster
+// through the target, pulls follow it back and stop short of the caster.
+fn forced_path(origin: Point, start: Point, distance: i32, toward: bool) -> Vec<Point> {
+    let (dx, dy) = (start.x - origin.x, start.y - origin.y);
+    let end = if toward {
+        origin
+    } else {
+        let len = i32::max(dx.abs(), dy.abs()) as f32;
+        Point::new(
+            start.x + (dx as f32 / len * distance as f32).round() as i32,
+            start.y + (dy as f32 / len * distance as f32).round() as i32,
+        )
+    };
+    rltk::line2d(rltk::LineAlg::Bresenham, start, end)
+        .into_iter()
+        .filter(|pt| *pt != start && *pt != origin)
+        .take(distance as usize)
+        .collect()
+}
+
+fn obstacle_at(ecs: &World, mover: Entity, pt: Point) -> Option<Obstacle> {
+    let map = ecs.fetch::<Map>();
+    if pt.x < 0 || pt.y < 0 || pt.x >= map.width || pt.y >= map.height {
+        return Some(Obstacle::Wall);
+    }
+    let idx = map.xy_idx(pt.x, pt.y);
+    if map.tiles[idx] == TileType::Wall {
+        return Some(Obstacle::Wall);
+    }
+    let blockers = ecs.read_storage::<BlocksTile>();
+    let players = ecs.read_storage::<Player>();
+    let standing = map.tile_content[idx]
+        .iter()
+        .find(|e| **e != mover && (blockers.get(**e).is_some() || players.get(**e).is_some()))
+        .map(|e| Obstacle::Entity(*e));
+    if standing.is_some() {
+        return standing;
+    }
+    // Positions only change once the movement system runs, so a tile something else in the same
+    // blast is already headed for counts as taken
+    let entities = ecs.entities();
+    let teleports = ecs.read_storage::<ApplyTeleport>();
+    (&entities, &teleports)
+        .join()
+        .find(|(e, dest)| *e != mover && dest.dest_x == pt.x && dest.dest_y == pt.y)
+        .map(|(e, _)| Obstacle::Entity(e))
+}
+
+// Anything solid can be shoved around, and so can the player, who doesn't block a tile
+fn movable(ecs: &World, entity: Entity) -> bool {
+    ecs.read_storage::<BlocksTile>().get(entity).is_some()
+        || ecs.read_storage::<Player>().get(entity).is_some()
+}
+
+// Push, pull and knockback. The target slides along the line from the caster until it runs
+// out of distance or hits something, in which case both sides take collision damage.
+pub fn forced_move(ecs: &mut World, effect: &EffectSpawner, target: Entity) {
+    let (distance, toward, staggers) = match effect.effect_type {
+        EffectType::Push { distance } => (distance, false, false),
+        EffectType::Pull { distance } => (distance, true, false),
+        EffectType::Knockback { distance } => (distance, false, true),
+        _ => return,
+    };
+    let Some(caster) = effect.creator else {
+        return;
+    };
+    if caster == target || !movable(ecs, target) {
+        return;
+    }
+    let (Some(origin), Some(start)) = (position_of(ecs, caster), position_of(ecs, target)) else {
+        return;
+    };
+    if origin == start {
+        return;
+    }
+
+    let mut dest = start;
+    let mut travelled = 0;
+    let mut obstacle = None;
+    for pt in forced_path(origin, start, distance, toward) {
+        obstacle = obstacle_at(ecs, target, pt);
+        if obstacle.is_some() {
+            break;
+        }
+        dest = pt;
+        travelled += 1;
+    }
+
+    if dest != start {
+        ecs.write_storage::<ApplyTeleport>()
+            .insert(
+                target,
+                ApplyTeleport {
+                    dest_x: dest.x,
+                    dest_y: dest.y,
+                },
+            )
+            .expect("Unable to insert teleport");
+        let (start_idx, dest_idx) = {
+            let map = ecs.fetch::<Map>();
+            (map.xy_idx(start.x, start.y), map.xy_idx(dest.x, dest.y))
+        };
+        triggers::spawn_line_particles(
+            ecs,
+            start_idx as i32,
+            dest_idx as i32,
+            &SpawnParticleLine {
+                glyph: rltk::to_cp437('·'),
+                color: COLORS.grey,
+                lifetime_ms: 150.0,
+            },
+        );
+    }
+
+    let remaining = distance - travelled;
+    let Some(obstacle) = obstacle else {
+        return;
+    };
+    // Pulls that stop at the caster's side have simply arrived
+    if remaining < 1 || (toward && matches!(obstacle, Obstacle::Entity(e) if e == caster)) {
+        return;
+    }
+
+    let amount = remaining * COLLISION_DAMAGE;
+    let target_name = name_of(ecs, target);
+    let hit = match obstacle {
+        Obstacle::Wall => "the wall".to_string(),
+        Obstacle::Entity(other) => {
+            add_effect(
+                ecs,
+                Some(caster),
+                EffectType::Damage { amount },
+                Targets::Single { target: other },
+            );
+            name_of(ecs, other)
+        }
+    };
+    add_effect(
+        ecs,
+        Some(caster),
+        EffectType::Damage { amount },
+        Targets::Single { target },
+    );
+    if staggers {
+        add_effect(
+            ecs,
+            Some(caster),
+            EffectType::ApplyStatus {
+                status: StatusType::Stun,
+                magnitude: 0,
+                duration: 1,
+            },
+            Targets::Single { target },
+        );
+    }
+    ecs.fetch_mut::<gamelog::GameLog>()
+        .entries
+        .push(format!("{} slams into {}", target_name, hit));
+}
+
+// The caster and the target trade places
+pub fn swap(ecs: &mut World, effect: &EffectSpawner, target: Entity) {
+    let Some(caster) = effect.creator else {
+        return;
+    };
+    if caster == target || !movable(ecs, target) {
+        return;
+    }
+    let (Some(origin), Some(dest)) = (position_of(ecs, caster), position_of(ecs, target)) else {
+        return;
+    };
+
+    let mut teleports = ecs.write_storage::<ApplyTeleport>();
+    teleports
+        .insert(
+            caster,
+            ApplyTeleport {
+                dest_x: dest.x,
+                dest_y: dest.y,
+            },
+        )
+        .expect("Unable to insert teleport");
+    teleports
+        .insert(
+            target,
+            ApplyTeleport {
+                dest_x: origin.x,
+                dest_y: origin.y,
+            },
+        )
+        .expect("Unable to insert teleport");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::Simulation;
+
+    #[test]
+    fn test_forced_path() {
+        let caster = Point::new(5, 5);
+        let target = Point::new(7, 5);
+        let push = forced_path(caster, target, 3, false);
+        assert_eq!(push, vec![Point::new(8, 5), Point::new(9, 5), Point::new(10, 5)]);
+
+        // Pulls stop next to the caster, however far they could have gone
+        let pull = forced_path(caster, target, 5, true);
+        assert_eq!(pull, vec![Point::new(6, 5)]);
+
+        let diagonal = forced_path(caster, Point::new(6, 6), 2, false);
+        assert_eq!(diagonal, vec![Point::new(7, 7), Point::new(8, 8)]);
+    }
+
+    #[test]
+    fn test_player_is_pushed() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let pos = sim.player_pos();
+        let troll = sim.spawn("Troll", pos.x - 1, pos.y);
+
+        let player = sim.player();
+        add_effect(
+            sim.ecs(),
+            Some(troll),
+            EffectType::Push { distance: 2 },
+            Targets::Single { target: player },
+        );
+        run_effects_queue(sim.ecs());
+        sim.state.run_systems();
+        assert_eq!(sim.player_pos(), Point::new(pos.x + 2, pos.y));
+
+        add_effect(
+            sim.ecs(),
+            Some(troll),
+            EffectType::Swap,
+            Targets::Single { target: player },
+        );
+        run_effects_queue(sim.ecs());
+        sim.state.run_systems();
+        assert_eq!(sim.player_pos(), Point::new(pos.x - 1, pos.y));
+    }
+
+    #[test]
+    fn test_pulled_together_without_stacking() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let pos = sim.player_pos();
+        // Pulled in from below or along the diagonal, both would end up on the same tile
+        let below = sim.spawn("Troll", pos.x + 1, pos.y + 2);
+        let diagonal = sim.spawn("Troll", pos.x + 2, pos.y + 2);
+        let paths = [below, diagonal].map(|troll| {
+            let from = sim.position(troll).unwrap();
+            forced_path(pos, from, 3, true)
+        });
+        assert_eq!(paths[0].last(), paths[1].last());
+
+        let player = sim.player();
+        add_effect(
+            sim.ecs(),
+            Some(player),
+            EffectType::Pull { distance: 3 },
+            Targets::TargetList {
+                targets: vec![below, diagonal],
+            },
+        );
+        run_effects_queue(sim.ecs());
+        sim.state.run_systems();
+        // The first one there gets the tile, and the other is stopped short by it
+        assert_eq!(sim.position(below), Some(Point::new(pos.x + 1, pos.y + 1)));
+        assert_eq!(
+            sim.position(diagonal),
+            Some(Point::new(pos.x + 2, pos.y + 2))
+        );
+    }
+}
@@ -4,7 +4,9 @@ use specs::prelude::*;
 
 pub fn lose_mana(ecs: &mut World, lose_mana: &EffectSpawner, target: Entity) {
     let mut entity_stats = ecs.write_storage::<EntityStats>();
-    if let Some(pool) = entity_stats.get_mut(target) {
+    // Not everything that drinks a potion has mana to gain or lose
+    let has_mana = |stats: &&mut EntityStats| stats.pools.contains_key("mana");
+    if let Some(pool) = entity_stats.get_mut(target).filter(has_mana) {
         if let EffectType::LoseMana { amount } = lose_mana.effect_type {
             let current_mana = pool.get("mana").0;
             if  current_mana >= amount {
@@ -19,6 +21,7 @@ pub fn lose_mana(ecs: &mut World, lose_mana: &EffectSpawner, target: Entity) {
                 pool.set_current("mana", 0);
                 let damage = 2 * (amount - current_mana);
                 add_effect(
+                    ecs,
                     None,
                     EffectType::Damage {amount: damage},
                     Targets::Single { target },
@@ -30,7 +33,9 @@ pub fn lose_mana(ecs: &mut World, lose_mana: &EffectSpawner, target: Entity) {
 
 pub fn gain_mana(ecs: &mut World, gain_mana: &EffectSpawner, target: Entity) {
     let mut entity_stats = ecs.write_storage::<EntityStats>();
-    if let Some(pool) = entity_stats.get_mut(target) {
+    // Not everything that drinks a potion has mana to gain or lose
+    let has_mana = |stats: &&mut EntityStats| stats.pools.contains_key("mana");
+    if let Some(pool) = entity_stats.get_mut(target).filter(has_mana) {
         if let EffectType::GainMana { amount } = gain_mana.effect_type {
             pool.restore("mana", amount);
         }
@@ -1,25 +1,27 @@
 mod damage;
+mod forced_movement;
 mod movement;
 mod particles;
 mod targeting;
 mod triggers;
 mod mana;
+mod status;
+mod summon;
 pub use targeting::*;
 
 use specs::prelude::*;
 use std::collections::VecDeque;
-use std::sync::Mutex;
 
-use crate::Map;
-
-lazy_static! {
-    pub static ref EFFECT_QUEUE: Mutex<VecDeque<EffectSpawner>> = Mutex::new(VecDeque::new());
-}
+use crate::{dice::Dice, Map, StatusType};
 
 pub enum EffectType {
     Damage {
         amount: i32,
     },
+    Attack {
+        damage: Dice,
+        source: String,
+    },
     Healing {
         amount: i32,
     },
@@ -33,16 +35,41 @@ pub enum EffectType {
     ItemUse {
         item: Entity,
     },
+    SpellUse {
+        spell: Entity,
+    },
     TeleportTo {
         x: i32,
         y: i32,
     },
+    Push {
+        distance: i32,
+    },
+    Pull {
+        distance: i32,
+    },
+    Knockback {
+        distance: i32,
+    },
+    Swap,
     GainMana {
         amount: i32,
     },
     LoseMana {
         amount: i32,
     },
+    ApplyStatus {
+        status: StatusType,
+        magnitude: i32,
+        duration: i32,
+    },
+    RemoveStatus {
+        status: StatusType,
+    },
+    Summon {
+        mob: String,
+        duration: i32,
+    },
 }
 
 #[derive(Clone)]
@@ -59,17 +86,34 @@ pub struct EffectSpawner {
     pub targets: Targets,
 }
 
-pub fn add_effect(creator: Option<Entity>, effect_type: EffectType, targets: Targets) {
-    EFFECT_QUEUE.lock().unwrap().push_back(EffectSpawner {
-        creator,
-        effect_type,
-        targets,
-    });
+/// Effects waiting to be applied. It lives in the World, so every game (or test) gets its own.
+#[derive(Default)]
+pub struct EffectQueue {
+    queue: VecDeque<EffectSpawner>,
+}
+
+impl EffectQueue {
+    pub fn add(&mut self, creator: Option<Entity>, effect_type: EffectType, targets: Targets) {
+        self.queue.push_back(EffectSpawner {
+            creator,
+            effect_type,
+            targets,
+        });
+    }
+}
+
+pub fn add_effect(
+    ecs: &World,
+    creator: Option<Entity>,
+    effect_type: EffectType,
+    targets: Targets,
+) {
+    ecs.fetch_mut::<EffectQueue>().add(creator, effect_type, targets);
 }
 
 pub fn run_effects_queue(ecs: &mut World) {
     loop {
-        let effect: Option<EffectSpawner> = EFFECT_QUEUE.lock().unwrap().pop_front();
+        let effect: Option<EffectSpawner> = ecs.fetch_mut::<EffectQueue>().queue.pop_front();
         if let Some(effect) = effect {
             target_applicator(ecs, &effect);
         } else {
@@ -81,6 +125,8 @@ pub fn run_effects_queue(ecs: &mut World) {
 fn target_applicator(ecs: &mut World, effect: &EffectSpawner) {
     if let EffectType::ItemUse { item } = effect.effect_type {
         triggers::item_trigger(effect.creator, item, &effect.targets, ecs);
+    } else if let EffectType::SpellUse { spell } = effect.effect_type {
+        triggers::spell_trigger(effect.creator, spell, &effect.targets, ecs);
     } else {
         match &effect.targets {
             Targets::Tile { tile_idx } => affect_tile(ecs, effect, *tile_idx),
@@ -98,9 +144,16 @@ fn target_applicator(ecs: &mut World, effect: &EffectSpawner) {
 fn tile_effect_hits_entities(effect: &EffectType) -> bool {
     match effect {
         EffectType::Damage { .. } => true,
+        EffectType::Attack { .. } => true,
         EffectType::Healing { .. } => true,
         EffectType::GainMana { .. } => true,
         EffectType::LoseMana { .. } => true,
+        EffectType::ApplyStatus { .. } => true,
+        EffectType::RemoveStatus { .. } => true,
+        EffectType::Push { .. } => true,
+        EffectType::Pull { .. } => true,
+        EffectType::Knockback { .. } => true,
+        EffectType::Swap => true,
         // EffectType::Particle { .. } => true,
         _ => false,
     }
@@ -115,8 +168,9 @@ fn affect_tile(ecs: &mut World, effect: &EffectSpawner, tile_idx: i32) {
     }
     match &effect.effect_type {
         EffectType::Bloodstain => damage::bloodstain(ecs, tile_idx),
-        EffectType::Particle { .. } => particles::particle_to_tile(ecs, tile_idx, &effect),
+        EffectType::Particle { .. } => particles::particle_to_tile(ecs, tile_idx, effect),
         EffectType::TeleportTo { .. } => movement::apply_teleport(ecs, effect, tile_idx),
+        EffectType::Summon { .. } => summon::summon(ecs, effect, tile_idx),
         _ => {}
     }
 }
@@ -125,18 +179,30 @@ fn affect_entity(ecs: &mut World, effect: &EffectSpawner, target: Entity) {
     // we write a lambda here to avoid borrowing ecs as mutable and immutable in the smae scope
     match &effect.effect_type {
         EffectType::Damage { .. } => damage::inflict_damage(ecs, effect, target),
+        EffectType::Attack { .. } => damage::attack(ecs, effect, target),
         EffectType::Healing { .. } => damage::heal_damage(ecs, effect, target),
         // we gain/lose mana based on targets, but it shouldnt' be so
         EffectType::GainMana { .. } => mana::gain_mana(ecs, effect, target),
         EffectType::LoseMana { .. } => mana::lose_mana(ecs, effect, target),
-        EffectType::Bloodstain { .. } => {
+        EffectType::ApplyStatus { .. } => status::apply_status(ecs, effect, target),
+        EffectType::RemoveStatus { .. } => status::remove_status(ecs, effect, target),
+        EffectType::Push { .. } => forced_movement::forced_move(ecs, effect, target),
+        EffectType::Pull { .. } => forced_movement::forced_move(ecs, effect, target),
+        EffectType::Knockback { .. } => forced_movement::forced_move(ecs, effect, target),
+        EffectType::Swap => forced_movement::swap(ecs, effect, target),
+        EffectType::Bloodstain => {
             if let Some(pos) = entity_position(ecs, target) {
                 damage::bloodstain(ecs, pos)
             }
         }
         EffectType::Particle { .. } => {
             if let Some(pos) = entity_position(ecs, target) {
-                particles::particle_to_tile(ecs, pos, &effect)
+                particles::particle_to_tile(ecs, pos, effect)
+            }
+        }
+        EffectType::Summon { .. } => {
+            if let Some(pos) = entity_position(ecs, target) {
+                summon::summon(ecs, effect, pos)
             }
         }
         _ => {}
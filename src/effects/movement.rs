@@ -1,21 +1,43 @@
 use super::*;
-use crate::{Player, Position, Viewshed, systems};
+use crate::{ApplyTeleport, BlocksTile, Position};
 use specs::prelude::*;
 
+// Teleports the creator to the tile. Anything already standing there is swapped into the
+// creator's old spot.
 pub fn apply_teleport(ecs: &mut World, destination: &EffectSpawner, tile_idx: i32) {
-    let map = ecs.fetch::<Map>();
-    let mut players = ecs.write_storage::<Player>();
-    let mut positions = ecs.write_storage::<Position>();
-    let mut viewsheds = ecs.write_storage::<Viewshed>();
-    let (x, y) = map.idx_xy(tile_idx);
     if let EffectType::TeleportTo { .. } = &destination.effect_type {
-        for (_player, pos, viewshed) in (&mut players, &mut positions, &mut viewsheds).join() {
-            pos.x = x;
-            pos.y = y;
-            viewshed.dirty = true;
-            // TODO: also recenter the camera
+        let Some(traveller) = destination.creator else {
+            return;
+        };
+        let Some(origin) = ecs.read_storage::<Position>().get(traveller).cloned() else {
+            return;
+        };
+
+        let occupants: Vec<Entity> = {
+            let map = ecs.fetch::<Map>();
+            let blockers = ecs.read_storage::<BlocksTile>();
+            map.tile_content[tile_idx as usize]
+                .iter()
+                .filter(|e| **e != traveller && blockers.get(**e).is_some())
+                .cloned()
+                .collect()
+        };
+
+        let (x, y) = ecs.fetch::<Map>().idx_xy(tile_idx);
+        let mut teleports = ecs.write_storage::<ApplyTeleport>();
+        teleports
+            .insert(traveller, ApplyTeleport { dest_x: x, dest_y: y })
+            .expect("Unable to insert teleport");
+        for occupant in occupants {
+            teleports
+                .insert(
+                    occupant,
+                    ApplyTeleport {
+                        dest_x: origin.x,
+                        dest_y: origin.y,
+                    },
+                )
+                .expect("Unable to insert teleport");
         }
-        //TODO: apply teleport needs a whole movement system
-        // TODO: allow for non-player things to tp too
     }
 }
@@ -13,7 +13,7 @@ pub fn particle_to_tile(ecs: &mut World, tile_idx: i32, effect: &EffectSpawner)
     {
         let map = ecs.fetch::<Map>();
         let mut particle_builder = ecs.fetch_mut::<ParticleBuilder>();
-        let (x,y) = map.idx_xy(tile_idx as i32);
+        let (x,y) = map.idx_xy(tile_idx);
         particle_builder.request(
             x,
             y,
@@ -0,0 +1,88 @@
+use super::*;
+use crate::{gamelog, Name, Status, StatusEffects};
+use specs::prelude::*;
+
+pub fn apply_status(ecs: &mut World, effect: &EffectSpawner, target: Entity) {
+    if let EffectType::ApplyStatus {
+        status,
+        magnitude,
+        duration,
+    } = effect.effect_type
+    {
+        let mut status_effects = ecs.write_storage::<StatusEffects>();
+        if status_effects.get(target).is_none() {
+            status_effects
+                .insert(target, StatusEffects::default())
+                .expect("Unable to insert status effects");
+        }
+        if let Some(effects) = status_effects.get_mut(target) {
+            effects.add(Status {
+                typ: status,
+                magnitude,
+                duration,
+            });
+        }
+
+        if let Some(name) = ecs.read_storage::<Name>().get(target) {
+            let mut gamelog = ecs.fetch_mut::<gamelog::GameLog>();
+            gamelog
+                .entries
+                .push(format!("{} is afflicted with {}", name.name, status.name()));
+        }
+    }
+}
+
+pub fn remove_status(ecs: &mut World, effect: &EffectSpawner, target: Entity) {
+    if let EffectType::RemoveStatus { status } = effect.effect_type {
+        let mut status_effects = ecs.write_storage::<StatusEffects>();
+        if let Some(effects) = status_effects.get_mut(target) {
+            if effects.has(status) {
+                effects.remove(status);
+                if let Some(name) = ecs.read_storage::<Name>().get(target) {
+                    let mut gamelog = ecs.fetch_mut::<gamelog::GameLog>();
+                    gamelog
+                        .entries
+                        .push(format!("{} is no longer afflicted with {}", name.name, status.name()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::raws::{spawn_named_spell, RAWS};
+    use crate::sim::Simulation;
+    use crate::{config, PlayerAction, PlayerInput, StatusEffects, StatusType};
+    use specs::prelude::*;
+
+    #[test]
+    fn test_charmed_monsters_change_sides() {
+        let mut sim = Simulation::quiet(["Enchantment", "Pyromancy"]);
+        let player = sim.player();
+        let pos = sim.player_pos();
+        let hotkey = config::CONFIG.hotkeys()[2].clone();
+        spawn_named_spell(
+            &RAWS.lock().unwrap(),
+            sim.ecs().create_entity(),
+            "Beguile",
+            hotkey,
+        );
+        let charmed = sim.spawn("Goblin", pos.x + 2, pos.y);
+        sim.spawn("Goblin", pos.x + 4, pos.y);
+
+        sim.cast_at("Beguile", pos.x + 2, pos.y);
+        let effects = sim.ecs().read_storage::<StatusEffects>();
+        assert!(effects.get(charmed).unwrap().has(StatusType::Charm));
+        drop(effects);
+        let before = sim.hit_points(player);
+        for _ in 0..3 {
+            sim.input(PlayerInput::Act(PlayerAction::Wait));
+        }
+        assert!(sim
+            .log()
+            .iter()
+            .any(|entry| entry.starts_with("Goblin hits Goblin")));
+        assert_eq!(sim.hit_points(player), before);
+    }
+}
@@ -0,0 +1,176 @@
+use super::*;
+use crate::raws::{spawn_named_entity, SpawnType, ALLY_FACTION, RAWS};
+use crate::{gamelog, Faction, Name, Player, Status, StatusEffects, StatusType, TileType};
+use rltk::Point;
+use specs::prelude::*;
+
+// How far from the chosen tile a summon can turn up, when something is already standing there
+const SUMMON_SPREAD: i32 = 2;
+
+// Calls up a mob on its summoner's side, which goes away again once its time runs out
+pub fn summon(ecs: &mut World, effect: &EffectSpawner, tile_idx: i32) {
+    if let EffectType::Summon { mob, duration } = &effect.effect_type {
+        let Some(at) = open_tile_near(ecs, tile_idx) else {
+            return;
+        };
+        let spawn = SpawnType::AtPosition { x: at.x, y: at.y };
+        let summoned = spawn_named_entity(&RAWS.lock().unwrap(), ecs.create_entity(), mob, spawn);
+        let Some(summoned) = summoned else {
+            return;
+        };
+        // Claimed, so the next summon from the same blast lands somewhere else
+        {
+            let mut map = ecs.fetch_mut::<Map>();
+            let idx = map.xy_idx(at.x, at.y);
+            map.blocked[idx] = true;
+        }
+
+        if let Some(side) = summoner_side(ecs, effect.creator) {
+            ecs.write_storage::<Faction>()
+                .insert(summoned, Faction { name: side })
+                .expect("Unable to insert faction");
+        }
+        let mut effects = StatusEffects::default();
+        effects.add(Status {
+            typ: StatusType::Summoned,
+            magnitude: 1,
+            duration: *duration,
+        });
+        ecs.write_storage::<StatusEffects>()
+            .insert(summoned, effects)
+            .expect("Unable to insert status effects");
+
+        if let Some(name) = ecs.read_storage::<Name>().get(summoned) {
+            let mut gamelog = ecs.fetch_mut::<gamelog::GameLog>();
+            gamelog
+                .entries
+                .push(format!("{} answers the call.", name.name));
+        }
+    }
+}
+
+// Whoever fights for the summoner right now. The player isn't a faction of its own to join, so
+// the player's summons are allies. Without a summoner the mob keeps its own faction.
+fn summoner_side(ecs: &World, creator: Option<Entity>) -> Option<String> {
+    let creator = creator?;
+    if ecs.read_storage::<Player>().get(creator).is_some() {
+        return Some(ALLY_FACTION.to_string());
+    }
+    let factions = ecs.read_storage::<Faction>();
+    let effects = ecs.read_storage::<StatusEffects>();
+    factions
+        .get(creator)
+        .map(|faction| faction.allegiance(effects.get(creator)).to_string())
+}
+
+fn open_tile_near(ecs: &World, tile_idx: i32) -> Option<Point> {
+    let map = ecs.fetch::<Map>();
+    let (x, y) = map.idx_xy(tile_idx);
+    let target = Point::new(x, y);
+    let mut open = Vec::new();
+    for y in target.y - SUMMON_SPREAD..=target.y + SUMMON_SPREAD {
+        for x in target.x - SUMMON_SPREAD..=target.x + SUMMON_SPREAD {
+            if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 {
+                continue;
+            }
+            let idx = map.xy_idx(x, y);
+            if map.tiles[idx] != TileType::Wall && !map.blocked[idx] {
+                open.push(Point::new(x, y));
+            }
+        }
+    }
+    let distance = |to: &Point| rltk::DistanceAlg::Pythagoras.distance2d(target, *to);
+    open.into_iter()
+        .min_by(|a, b| distance(a).partial_cmp(&distance(b)).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::Simulation;
+    use crate::{PlayerAction, PlayerInput, Position};
+
+    #[test]
+    fn test_summons_fight() {
+        let mut sim = Simulation::quiet(["Conjuration", "Pyromancy"]);
+        let pos = sim.player_pos();
+        sim.spawn("Goblin", pos.x + 4, pos.y);
+        sim.cast_at("Summon Wolf", pos.x + 1, pos.y);
+        let wolf = sim.named("Wolf").expect("Nothing answered the call");
+        assert_eq!(
+            sim.ecs().read_storage::<Faction>().get(wolf).unwrap().name,
+            "Allies"
+        );
+
+        for _ in 0..5 {
+            sim.input(PlayerInput::Act(PlayerAction::Wait));
+        }
+        assert!(sim
+            .log()
+            .iter()
+            .any(|entry| entry.starts_with("Wolf hits Goblin")));
+    }
+
+    #[test]
+    fn test_summons_fade() {
+        let mut sim = Simulation::quiet(["Conjuration", "Pyromancy"]);
+        let pos = sim.player_pos();
+        sim.cast_at("Summon Wolf", pos.x + 1, pos.y);
+        let wolf = sim.named("Wolf").unwrap();
+        let effects = sim.ecs().read_storage::<StatusEffects>();
+        assert!(effects.get(wolf).unwrap().has(StatusType::Summoned));
+        drop(effects);
+
+        for _ in 0..20 {
+            sim.input(PlayerInput::Act(PlayerAction::Wait));
+        }
+        assert!(!sim.ecs().is_alive(wolf));
+        assert!(sim.log().iter().any(|entry| entry == "Wolf fades away."));
+    }
+
+    #[test]
+    fn test_summons_take_their_summoners_side() {
+        let mut sim = Simulation::quiet(["Conjuration", "Pyromancy"]);
+        let pos = sim.player_pos();
+        let summon_for = |sim: &mut Simulation, creator: Entity, x: i32| {
+            let tile_idx = sim.ecs().fetch::<Map>().xy_idx(x, pos.y + 3) as i32;
+            add_effect(
+                sim.ecs(),
+                Some(creator),
+                EffectType::Summon {
+                    mob: "Wolf".to_string(),
+                    duration: 10,
+                },
+                Targets::Tile { tile_idx },
+            );
+            run_effects_queue(sim.ecs());
+            let wolves = sim.state.ecs.read_storage::<Name>();
+            let factions = sim.state.ecs.read_storage::<Faction>();
+            let positions = sim.state.ecs.read_storage::<Position>();
+            (&wolves, &factions, &positions)
+                .join()
+                .find(|(name, _, at)| name.name == "Wolf" && at.x == x)
+                .map(|(_, faction, _)| faction.name.clone())
+                .expect("Nothing answered the call")
+        };
+
+        let goblin = sim.spawn("Goblin", pos.x + 4, pos.y);
+        assert_eq!(summon_for(&mut sim, goblin, pos.x - 4), "Greenskins");
+
+        // A charmed summoner calls up help for the side it's on now
+        let mut effects = StatusEffects::default();
+        effects.add(Status {
+            typ: StatusType::Charm,
+            magnitude: 1,
+            duration: 5,
+        });
+        sim.ecs()
+            .write_storage::<StatusEffects>()
+            .insert(goblin, effects)
+            .unwrap();
+        assert_eq!(summon_for(&mut sim, goblin, pos.x), ALLY_FACTION);
+
+        let player = sim.player();
+        assert_eq!(summon_for(&mut sim, player, pos.x + 4), ALLY_FACTION);
+    }
+}
@@ -2,7 +2,8 @@ use specs::prelude::*;
 
 use crate::{
     Consumable, InflictsDamage, ProvidesHealing, SpawnParticleBurst, SpawnParticleLine,
-    TeleportTo, COLORS, ProvidesMana, CostsMana,
+    TeleportTo, COLORS, ProvidesMana, CostsMana, InflictsStatus, CuresStatus, Pushes, Pulls,
+    KnocksBack, SwapsPlaces, Summons, Name,
 };
 
 use super::*;
@@ -17,7 +18,12 @@ pub fn item_trigger(creator: Option<Entity>, item: Entity, targets: &Targets, ec
     }
 }
 
-fn spawn_line_particles(ecs: &World, start: i32, end: i32, part: &SpawnParticleLine) {
+pub fn spell_trigger(creator: Option<Entity>, spell: Entity, targets: &Targets, ecs: &mut World) {
+    // Spells are never used up, so we don't care whether anything happened
+    event_trigger(creator, spell, targets, ecs);
+}
+
+pub fn spawn_line_particles(ecs: &World, start: i32, end: i32, part: &SpawnParticleLine) {
     let map = ecs.fetch::<Map>();
     let (start_x, start_y) = map.idx_xy(start);
     let (end_x, end_y) = map.idx_xy(end);
@@ -28,6 +34,7 @@ fn spawn_line_particles(ecs: &World, start: i32, end: i32, part: &SpawnParticleL
     let line = rltk::line2d(rltk::LineAlg::Bresenham, start_pt, end_pt);
     for pt in line.iter() {
         add_effect(
+            ecs,
             None,
             EffectType::Particle {
                 glyph: part.glyph,
@@ -53,6 +60,7 @@ fn event_trigger(
     // Simple particle spawn
     if let Some(part) = ecs.read_storage::<SpawnParticleBurst>().get(entity) {
         add_effect(
+            ecs,
             creator,
             EffectType::Particle {
                 glyph: part.glyph,
@@ -65,7 +73,10 @@ fn event_trigger(
     }
     // Line particle spawn
     if let Some(part) = ecs.read_storage::<SpawnParticleLine>().get(entity) {
-        if let Some(start_pos) = targeting::find_item_position(ecs, entity) {
+        // Spells have no position of their own, so lines start at the caster
+        let start_pos = targeting::find_item_position(ecs, entity)
+            .or_else(|| creator.and_then(|caster| entity_position(ecs, caster)));
+        if let Some(start_pos) = start_pos {
             match targets {
                 Targets::Tile { tile_idx } => spawn_line_particles(ecs, start_pos, *tile_idx, part),
                 Targets::Tiles { tiles } => tiles
@@ -87,17 +98,20 @@ fn event_trigger(
         }
     }
 
-    // we write a lambda here to avoid borrowing ecs as mutable and immutable in the smae scope
-    let get_player_target = |ecs: &mut World| Targets::Single{target:*ecs.fetch::<Entity>()};
-    let player_target = get_player_target(ecs);
+    // Healing, mana and cures go to whoever used the item or cast the spell
+    let self_target = match creator {
+        Some(user) => Targets::Single { target: user },
+        None => targets.clone(),
+    };
     // Healing
     if let Some(heal) = ecs.read_storage::<ProvidesHealing>().get(entity) {
         add_effect(
+            ecs,
             creator,
             EffectType::Healing {
                 amount: heal.heal_amount,
             },
-            player_target.clone(),
+            self_target.clone(),
         );
         did_something = true;
     }
@@ -105,31 +119,118 @@ fn event_trigger(
     // Gain Mana
     if let Some(mana) = ecs.read_storage::<ProvidesMana>().get(entity) {
         add_effect(
+            ecs,
             creator,
             EffectType::GainMana {
                 amount: mana.mana_amount,
             },
-            player_target.clone(),
+            self_target.clone(),
         );
         did_something = true;
     }
     // Lose Mana
     if let Some(mana) = ecs.read_storage::<CostsMana>().get(entity) {
         add_effect(
+            ecs,
             creator,
             EffectType::LoseMana {
                 amount: mana.mana_amount,
             },
-            player_target.clone(),
+            self_target.clone(),
         );
         did_something = true;
     }
     // Damage
     if let Some(damage) = ecs.read_storage::<InflictsDamage>().get(entity) {
+        let source = ecs
+            .read_storage::<Name>()
+            .get(entity)
+            .map_or_else(String::new, |name| name.name.clone());
+        add_effect(
+            ecs,
+            creator,
+            EffectType::Attack {
+                damage: damage.damage,
+                source,
+            },
+            targets.clone(),
+        );
+        did_something = true;
+    }
+
+    // Apply a status
+    if let Some(inflicts) = ecs.read_storage::<InflictsStatus>().get(entity) {
+        add_effect(
+            ecs,
+            creator,
+            EffectType::ApplyStatus {
+                status: inflicts.typ,
+                magnitude: inflicts.magnitude,
+                duration: inflicts.duration,
+            },
+            targets.clone(),
+        );
+        did_something = true;
+    }
+
+    // Cure a status
+    if let Some(cures) = ecs.read_storage::<CuresStatus>().get(entity) {
+        add_effect(
+            ecs,
+            creator,
+            EffectType::RemoveStatus { status: cures.typ },
+            self_target.clone(),
+        );
+        did_something = true;
+    }
+
+    // Forced movement
+    if let Some(push) = ecs.read_storage::<Pushes>().get(entity) {
+        add_effect(
+            ecs,
+            creator,
+            EffectType::Push {
+                distance: push.distance,
+            },
+            targets.clone(),
+        );
+        did_something = true;
+    }
+    if let Some(pull) = ecs.read_storage::<Pulls>().get(entity) {
+        add_effect(
+            ecs,
+            creator,
+            EffectType::Pull {
+                distance: pull.distance,
+            },
+            targets.clone(),
+        );
+        did_something = true;
+    }
+    if let Some(knockback) = ecs.read_storage::<KnocksBack>().get(entity) {
+        add_effect(
+            ecs,
+            creator,
+            EffectType::Knockback {
+                distance: knockback.distance,
+            },
+            targets.clone(),
+        );
+        did_something = true;
+    }
+    if ecs.read_storage::<SwapsPlaces>().get(entity).is_some() {
+        add_effect(ecs, creator, EffectType::Swap, targets.clone());
+        did_something = true;
+    }
+
+    // Summoning
+    if let Some(summons) = ecs.read_storage::<Summons>().get(entity) {
         add_effect(
+            ecs,
             creator,
-            EffectType::Damage {
-                amount: damage.damage,
+            EffectType::Summon {
+                mob: summons.mob.clone(),
+                duration: summons.duration,
             },
             targets.clone(),
         );
@@ -139,6 +240,7 @@ fn event_trigger(
     // Teleport
     if let Some(teleport) = ecs.read_storage::<TeleportTo>().get(entity) {
         add_effect(
+            ecs,
             creator,
             EffectType::TeleportTo {
                 x: teleport.x,
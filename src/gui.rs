@@ -2,35 +2,74 @@ use std::cmp::min;
 use std::collections::HashMap;
 
 use itertools::Itertools;
-use rltk::{Point, Rltk, RGB, to_cp437};
+use rltk::{Point, Rltk, VirtualKeyCode, RGB, to_cp437};
 use specs::prelude::*;
 
-use crate::config::{BOUNDS, INPUT};
-use crate::{camera, Map, COLORS};
+use crate::config::{BOUNDS, CONFIG, INPUT};
+use crate::raws::{get_schools, RAWS};
+use crate::systems::save_load::{SavedGames, SlotStatus};
+use crate::{
+    camera, describe, free_hotkeys, inventory_stacks, level_up_choices, systems, Map, PlayerAction, RunSeed,
+    COLORS,
+};
 
 use super::{components, GameLog, Player, RunState, State};
 pub use components::*;
 
-// TODO: this shouldn't live here
-pub const SCHOOLS: [&str; 3] = [
-    "unimplemented magical discipline 1",
-    "unimplemented magical discipline 2",
-    "unimplemented magical discipline 3",
-];
-
 #[derive(PartialEq, Copy, Clone)]
 pub enum MainMenuSelection {
     NewGame,
+    NewGameWithSeed,
     Continue,
+    WatchReplay,
     Quit,
 }
 
+/// Something the main menu has to tell the player, like why their save wouldn't load
+#[derive(Default)]
+pub struct MenuNotice {
+    pub text: Option<String>,
+}
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum MainMenuResult {
     NoSelection { selected: MainMenuSelection },
     Selected { selected: MainMenuSelection },
 }
 
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum InventorySort {
+    Name,
+    Quantity,
+}
+
+impl InventorySort {
+    fn next(self) -> InventorySort {
+        match self {
+            InventorySort::Name => InventorySort::Quantity,
+            InventorySort::Quantity => InventorySort::Name,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            InventorySort::Name => "name",
+            InventorySort::Quantity => "quantity",
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum InventoryAction {
+    Cancel,
+    NoResponse,
+    Up,
+    Down,
+    Sort { sort: InventorySort },
+    Inspect,
+    Act(PlayerAction),
+}
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum MenuAction {
     Cancel,
@@ -94,12 +133,7 @@ fn draw_resource_bar(ctx: &mut Rltk, stats: &EntityStats, resource_name: &str, x
     let percent = current as f32 / max as f32;
     let fill_width = (percent * bar_right as f32) as usize;
     for x in 0..bar_right{
-        let glyph;
-        if x < fill_width {
-             glyph = '▓';
-        } else {
-            glyph = '░';
-        }
+        let glyph = if x < fill_width { '▓' } else { '░' };
         ctx.set(bar_left + x, y, color, COLORS.black, to_cp437(glyph));
     }
 }
@@ -128,11 +162,75 @@ pub fn draw_char_ui(ecs: &World, ctx: &mut Rltk) {
     for (_player, stats) in (&players, &combat_stats).join() {
         draw_resource_bar(ctx, stats, "hit_points", ui_start_x+1, ui_start_y+1, COLORS.red);
         draw_resource_bar(ctx, stats, "mana", ui_start_x+1, ui_start_y+2, COLORS.cyan);
+        draw_resource_bar(ctx, stats, "xp", ui_start_x+1, ui_start_y+3, COLORS.yellow);
+    }
+    let schools = ecs.read_storage::<MagicSchools>();
+    for (_player, stats, schools) in (&players, &combat_stats, &schools).join() {
+        ctx.print_color(
+            ui_start_x + 1,
+            ui_start_y + 4,
+            COLORS.magenta,
+            COLORS.black,
+            format!("Level {} {}", stats.level, schools.names.join(" / ")),
+        );
     }
 
-    //inventory
     let player_entity = ecs.fetch::<Entity>();
+
+    // hotkeys
+    draw_horizontal_line(
+        ctx,
+        ui_start_x,
+        5,
+        ui_width as i32,
+        COLORS.white,
+        COLORS.black,
+        true,
+    );
+    ctx.print_color(ui_start_x + 1, 5, COLORS.yellow, COLORS.black, "Spells");
+
+    let spell_start = 6;
+    let spells = ecs.read_storage::<Spell>();
     let names = ecs.read_storage::<Name>();
+    let mana_costs = ecs.read_storage::<CostsMana>();
+    let spell_list = (&spells, &names, mana_costs.maybe())
+        .join()
+        .sorted_by(|a, b| Ord::cmp(&a.0.hotkey, &b.0.hotkey));
+    for (y, (spell, name, cost)) in spell_list.enumerate() {
+        let mut line = format!("{}: {}", spell.hotkey, name.name);
+        if let Some(cost) = cost {
+            line = format!("{} ({} mana)", line, cost.mana_amount);
+        }
+        ctx.print_color(ui_start_x + 1, y + spell_start, COLORS.cyan, COLORS.black, line);
+    }
+
+    // status effects
+    draw_horizontal_line(
+        ctx,
+        ui_start_x,
+        12,
+        ui_width as i32,
+        COLORS.white,
+        COLORS.black,
+        true,
+    );
+    ctx.print_color(ui_start_x + 1, 12, COLORS.yellow, COLORS.black, "Status Effects");
+
+    let status_start = 13;
+    let status_effects = ecs.read_storage::<StatusEffects>();
+    if let Some(effects) = status_effects.get(*player_entity) {
+        for (y, status) in effects.statuses.iter().enumerate() {
+            ctx.print_color(
+                ui_start_x + 1,
+                y + status_start,
+                COLORS.magenta,
+                COLORS.black,
+                format!("{} {} ({} turns)", status.typ.name(), status.magnitude, status.duration),
+            );
+        }
+    }
+
+    //inventory
     let backpack = ecs.read_storage::<InBackpack>();
     let entities = ecs.entities();
     let inventory = (&backpack, &names, &entities)
@@ -155,13 +253,19 @@ pub fn draw_char_ui(ecs: &World, ctx: &mut Rltk) {
     let just_names: Vec<&String> = inventory.into_iter().map(|el| &el.1.name).collect();
     let distinct_counts = count_strings(just_names);
 
+    let hotkeys = free_hotkeys(ecs);
+
     for (y, item) in distinct_counts.iter().enumerate() {
+        let line = match hotkeys.get(y) {
+            Some(hotkey) => format!("{}: {}", hotkey, item),
+            None => format!("   {}", item),
+        };
         ctx.print_color(
             ui_start_x + 1,
             y + inventory_start,
             COLORS.white,
             COLORS.black,
-            &item.to_string(),
+            line,
         );
     }
 }
@@ -184,24 +288,160 @@ pub fn draw_world_ui(ecs: &World, ctx: &mut Rltk) {
         COLORS.black,
     );
     let map = ecs.fetch::<Map>();
-    let depth = format!("Depth: {}", map.depth);
+    let turns = ecs.fetch::<systems::initiative::TurnCounter>().turns;
+    let depth = format!("Depth: {}  Turn: {}", map.depth, turns);
     ctx.print_color(ui_start_x + 1, 1, COLORS.yellow, COLORS.black, &depth);
+    let seed = format!("Seed: {}", ecs.fetch::<RunSeed>().seed);
+    ctx.print_color(ui_start_x + 1, 2, COLORS.grey, COLORS.black, &seed);
 
     let history = 20;
     let log = ecs.fetch::<GameLog>();
     let log_start = ui_height - min(history, log.entries.len()) - 1;
 
+    // cursor tile description
+    if let Some(tile) = examined_tile(ecs, ctx) {
+        draw_horizontal_line(
+            ctx,
+            ui_start_x as i32,
+            3,
+            ui_width as i32,
+            COLORS.white,
+            COLORS.black,
+            true,
+        );
+        ctx.print_color(ui_start_x + 1, 3, COLORS.yellow, COLORS.black, "Looking at");
+        let lines = tile_description(ecs, tile, UI_WIDTH - 2);
+        for (y, (line, color)) in (4..log_start.saturating_sub(1)).zip(lines) {
+            ctx.print_color(ui_start_x + 1, y, color, COLORS.black, line);
+        }
+    }
+
     let to_print = log
         .entries
         .iter()
         .rev()
         .flat_map(|s| wrap_text(s, UI_WIDTH - 2));
-    let mut y = log_start;
-    for s in to_print {
+    for (y, s) in (log_start..).zip(to_print) {
         if y < ui_height - 1 {
             ctx.print(ui_start_x + 1, y, s);
         }
-        y += 1;
+    }
+}
+
+/// The tile the sidebar describes: the examine cursor's while examining, otherwise whatever
+/// the mouse is over
+fn examined_tile(ecs: &World, ctx: &Rltk) -> Option<Point> {
+    if let RunState::Examine = *ecs.fetch::<RunState>() {
+        let cursor = ecs.fetch::<Cursor>().point;
+        return Some(camera::screen_to_tile(ecs, cursor));
+    }
+    mouse_over_view(ctx).map(|point| camera::screen_to_tile(ecs, point))
+}
+
+/// Where the mouse is in the map view, in the same screen coordinates the cursor uses
+fn mouse_over_view(ctx: &Rltk) -> Option<Point> {
+    let (x, y) = ctx.mouse_pos();
+    let x = x - UI_WIDTH as i32;
+    if x < 0 || x >= BOUNDS.view_width as i32 || y < 0 || y >= BOUNDS.view_height as i32 {
+        return None;
+    }
+    Some(Point::new(x, y))
+}
+
+/// The ground at a tile, then everything visible on it with its health and flavour
+fn tile_description(ecs: &World, tile: Point, width: usize) -> Vec<(String, RGB)> {
+    let mut lines = Vec::new();
+    match describe::terrain(ecs, tile) {
+        Some(terrain) => lines.push((terrain, COLORS.white)),
+        None => lines.push(("Unexplored".to_string(), COLORS.grey)),
+    }
+    for sighting in describe::sightings(ecs, tile) {
+        let name = match sighting.health {
+            Some((current, max)) => format!("{} ({}/{} HP)", sighting.name, current, max),
+            None => sighting.name,
+        };
+        lines.push((name, COLORS.cyan));
+        if let Some(flavour) = sighting.flavour {
+            for line in wrap_text(&flavour, width) {
+                lines.push((line, COLORS.grey));
+            }
+        }
+    }
+    lines
+}
+
+/// Names what's under the mouse in a small box beside it
+pub fn draw_tooltip(ecs: &World, ctx: &mut Rltk) {
+    let point = match mouse_over_view(ctx) {
+        Some(point) => point,
+        None => return,
+    };
+    let tile = camera::screen_to_tile(ecs, point);
+    let names = describe::sightings(ecs, tile)
+        .into_iter()
+        .map(|sighting| match sighting.health {
+            Some((current, max)) => format!("{} ({}/{})", sighting.name, current, max),
+            None => sighting.name,
+        })
+        .collect::<Vec<_>>();
+    if names.is_empty() {
+        return;
+    }
+
+    let width = names
+        .iter()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let view_right = UI_WIDTH + BOUNDS.view_width;
+    let mouse_x = point.x as usize + UI_WIDTH;
+    // Keep to whichever side of the mouse has room
+    let start_x = if mouse_x + 2 + width < view_right {
+        mouse_x + 2
+    } else {
+        mouse_x.saturating_sub(width + 2)
+    };
+    let height = names.len() + 1;
+    let lowest = BOUNDS.view_height.saturating_sub(height + 1);
+    let start_y = min(point.y as usize, lowest);
+    ctx.draw_box(start_x, start_y, width, height, COLORS.white, COLORS.black);
+    for (y, name) in (start_y + 1..).zip(names) {
+        ctx.print_color(start_x + 1, y, COLORS.white, COLORS.black, name);
+    }
+}
+
+/// Moves the examine cursor around the map view. The sidebar describes whatever it's on.
+pub fn examine(ecs: &mut World, ctx: &mut Rltk) -> MenuAction {
+    let mut cursor = ecs.fetch_mut::<Cursor>();
+
+    ctx.print_color(5, 0, COLORS.yellow, COLORS.black, "Examine:");
+    camera::set_bg_view(ctx, cursor.point.x, cursor.point.y, COLORS.cyan);
+
+    let max_x = BOUNDS.view_width as i32 - 1;
+    let max_y = BOUNDS.view_height as i32 - 1;
+    match ctx.key {
+        None => MenuAction::NoResponse,
+        Some(key) => match key {
+            _ if key == INPUT.exit || key == INPUT.inspect => MenuAction::Cancel,
+            _ if key == INPUT.up => {
+                cursor.point.y = (cursor.point.y - 1).max(0);
+                MenuAction::NoResponse
+            }
+            _ if key == INPUT.down => {
+                cursor.point.y = (cursor.point.y + 1).min(max_y);
+                MenuAction::NoResponse
+            }
+            _ if key == INPUT.left => {
+                cursor.point.x = (cursor.point.x - 1).max(0);
+                MenuAction::NoResponse
+            }
+            _ if key == INPUT.right => {
+                cursor.point.x = (cursor.point.x + 1).min(max_x);
+                MenuAction::NoResponse
+            }
+            _ => MenuAction::NoResponse,
+        },
     }
 }
 
@@ -290,6 +530,7 @@ pub fn chargen_menu(
     _gs: &mut State,
     ctx: &mut Rltk,
     selection: usize,
+    first_pick: Option<usize>,
 ) -> (MenuAction, Option<usize>) {
     let fgcolor = COLORS.white;
     let bgcolor = COLORS.black;
@@ -301,21 +542,81 @@ pub fn chargen_menu(
     let halfwidth = width / 2;
     ctx.draw_box(0, 0, halfwidth, height, fgcolor, bgcolor);
     ctx.draw_box(halfwidth + 1, 0, halfwidth - 1, height, fgcolor, bgcolor);
-    ctx.print_color_centered(0, COLORS.yellow, COLORS.black, "Choose a magical discipline");
+    let title = match first_pick {
+        None => "Choose your first magical discipline",
+        Some(_) => "Choose your second magical discipline",
+    };
+    ctx.print_color_centered(0, COLORS.yellow, COLORS.black, title);
+
+    let raws = RAWS.lock().unwrap();
+    let schools = get_schools(&raws);
 
     let inv_offset = 2;
-    for (y, school) in SCHOOLS.iter().enumerate() {
+    for (y, school) in schools.iter().enumerate() {
         let mut color = fgcolor;
+        if Some(y) == first_pick {
+            color = COLORS.cyan;
+        }
         if y == selection {
             color = hlcolor;
         }
-        ctx.print_color(
-            inv_offset,
-            y + inv_offset,
-            color,
-            bgcolor,
-            &school.to_string(),
-        );
+        ctx.print_color(inv_offset, y + inv_offset, color, bgcolor, &school.name);
+    }
+
+    // Details of the highlighted school
+    if let Some(school) = schools.get(selection) {
+        let desc_x = halfwidth + 3;
+        let desc_width = halfwidth - 5;
+        let mut y = inv_offset;
+        ctx.print_color(desc_x, y, COLORS.yellow, bgcolor, &school.name);
+        y += 2;
+        for line in wrap_text(&school.description, desc_width) {
+            ctx.print_color(desc_x, y, fgcolor, bgcolor, line);
+            y += 1;
+        }
+        y += 1;
+        let starting = format!("Starting spells: {}", school.starting_spells.join(", "));
+        ctx.print_color(desc_x, y, COLORS.cyan, bgcolor, starting);
+        y += 1;
+        let learnable = format!("Later spells: {}", school.spells.join(", "));
+        ctx.print_color(desc_x, y, COLORS.cyan, bgcolor, learnable);
+        y += 1;
+        if let Some(modifiers) = &school.stat_modifiers {
+            for (stat, amount) in modifiers.iter().sorted() {
+                ctx.print_color(desc_x, y, COLORS.green, bgcolor, format!("{:+} {}", amount, stat));
+                y += 1;
+            }
+        }
+    }
+
+    match ctx.key {
+        None => (MenuAction::NoResponse, None),
+        Some(key) => match key {
+            _ if key == INPUT.exit => (MenuAction::Cancel, None),
+            _ if key == INPUT.up && selection > 0 => (MenuAction::Up, None),
+            _ if key == INPUT.down && selection < schools.len() - 1 => (MenuAction::Down, None),
+            _ if key == INPUT.select => (MenuAction::Selected, Some(selection)),
+            _ => (MenuAction::NoResponse, None),
+        },
+    }
+}
+
+pub fn level_up_menu(gs: &mut State, ctx: &mut Rltk, selection: usize) -> (MenuAction, Option<usize>) {
+    let choices = level_up_choices(&gs.ecs);
+
+    let width = 40;
+    let height = choices.len() + 3;
+    let start_x = (BOUNDS.win_width - width) / 2;
+    let start_y = 15;
+    ctx.draw_box(start_x, start_y, width, height, COLORS.white, COLORS.black);
+    ctx.print_color(start_x + 2, start_y, COLORS.yellow, COLORS.black, "Level Up! Choose a boon");
+
+    for (y, choice) in choices.iter().enumerate() {
+        let mut color = COLORS.white;
+        if y == selection {
+            color = COLORS.magenta;
+        }
+        ctx.print_color(start_x + 2, start_y + 2 + y, color, COLORS.black, choice.describe());
     }
 
     match ctx.key {
@@ -323,13 +624,241 @@ pub fn chargen_menu(
         Some(key) => match key {
             _ if key == INPUT.exit => (MenuAction::Cancel, None),
             _ if key == INPUT.up && selection > 0 => (MenuAction::Up, None),
-            _ if key == INPUT.down && selection < SCHOOLS.len() - 1 => (MenuAction::Down, None),
+            _ if key == INPUT.down && selection < choices.len() - 1 => (MenuAction::Down, None),
             _ if key == INPUT.select => (MenuAction::Selected, Some(selection)),
             _ => (MenuAction::NoResponse, None),
         },
     }
 }
 
+const INVENTORY_ROWS: usize = 20;
+
+/// The player's inventory. The highlighted item can be used, dropped, thrown or looked at more
+/// closely, and the list can be sorted by name or by how many of each there are.
+pub fn inventory_menu(
+    ecs: &World,
+    ctx: &mut Rltk,
+    selection: usize,
+    sort: InventorySort,
+    inspecting: bool,
+) -> InventoryAction {
+    let stacks = inventory_stacks(ecs);
+    // Actions go by each stack's place in name order, whatever order it's shown in
+    let mut order: Vec<usize> = (0..stacks.len()).collect();
+    if sort == InventorySort::Quantity {
+        order.sort_by_key(|idx| std::cmp::Reverse(stacks[*idx].count));
+    }
+
+    let width = 76;
+    let height = INVENTORY_ROWS + 5;
+    let start_x = (BOUNDS.win_width - width) / 2;
+    let start_y = 10;
+    ctx.draw_box(start_x, start_y, width, height, COLORS.white, COLORS.black);
+    ctx.print_color(
+        start_x + 2,
+        start_y,
+        COLORS.yellow,
+        COLORS.black,
+        format!("Inventory (by {})", sort.label()),
+    );
+    if stacks.is_empty() {
+        ctx.print_color(
+            start_x + 2,
+            start_y + 2,
+            COLORS.white,
+            COLORS.black,
+            "You aren't carrying anything.",
+        );
+    }
+
+    let first_row = selection.saturating_sub(INVENTORY_ROWS - 1);
+    let list_width = 28;
+    for (row, idx) in order.iter().skip(first_row).take(INVENTORY_ROWS).enumerate() {
+        let stack = &stacks[*idx];
+        let color = if row + first_row == selection {
+            COLORS.magenta
+        } else {
+            COLORS.white
+        };
+        let y = start_y + 2 + row;
+        let line = format!("{} {}", stack.count, stack.name);
+        ctx.print_color(start_x + 2, y, color, COLORS.black, line);
+        if !inspecting {
+            let mut summary = describe::mechanics(ecs, stack.item).join(", ");
+            summary.truncate(width - list_width - 3);
+            ctx.print_color(start_x + 2 + list_width, y, COLORS.grey, COLORS.black, summary);
+        }
+    }
+
+    // A closer look at the highlighted item
+    let highlighted = order.get(selection).map(|idx| &stacks[*idx]);
+    if let (true, Some(stack)) = (inspecting, highlighted) {
+        let x = start_x + 2 + list_width;
+        ctx.print_color(x, start_y + 2, COLORS.yellow, COLORS.black, &stack.name);
+        let carried = format!("Carrying {}", stack.count);
+        ctx.print_color(x, start_y + 3, COLORS.white, COLORS.black, carried);
+        let mut y = start_y + 5;
+        if let Some(flavour) = describe::flavour(ecs, stack.item) {
+            for line in wrap_text(&flavour, width - list_width - 4) {
+                ctx.print_color(x, y, COLORS.white, COLORS.black, line);
+                y += 1;
+            }
+            y += 1;
+        }
+        for line in describe::mechanics(ecs, stack.item) {
+            ctx.print_color(x, y, COLORS.cyan, COLORS.black, line);
+            y += 1;
+        }
+    }
+
+    let hints = format!(
+        "[{}] use  [{}] drop  [{}] throw  [{}] inspect  [{}] sort  [{}] close",
+        CONFIG.select, CONFIG.drop, CONFIG.throw, CONFIG.inspect, CONFIG.sort, CONFIG.exit
+    );
+    ctx.print_color(start_x + 2, start_y + height - 1, COLORS.grey, COLORS.black, hints);
+
+    let index = match order.get(selection) {
+        Some(index) => *index,
+        None => {
+            return match ctx.key {
+                Some(key) if key == INPUT.exit || key == INPUT.inventory => InventoryAction::Cancel,
+                _ => InventoryAction::NoResponse,
+            };
+        }
+    };
+    match ctx.key {
+        None => InventoryAction::NoResponse,
+        Some(key) => match key {
+            _ if key == INPUT.exit || key == INPUT.inventory => InventoryAction::Cancel,
+            _ if key == INPUT.up && selection > 0 => InventoryAction::Up,
+            _ if key == INPUT.down && selection + 1 < stacks.len() => InventoryAction::Down,
+            _ if key == INPUT.sort => InventoryAction::Sort { sort: sort.next() },
+            _ if key == INPUT.inspect => InventoryAction::Inspect,
+            _ if key == INPUT.select => InventoryAction::Act(PlayerAction::UseItem { index }),
+            _ if key == INPUT.drop => InventoryAction::Act(PlayerAction::DropItem { index }),
+            _ if key == INPUT.throw => InventoryAction::Act(PlayerAction::ThrowItem { index }),
+            _ => InventoryAction::NoResponse,
+        },
+    }
+}
+
+fn draw_menu_notice(ecs: &World, ctx: &mut Rltk) {
+    if let Some(text) = &ecs.fetch::<MenuNotice>().text {
+        ctx.print_color_centered(31, COLORS.red, COLORS.black, text);
+    }
+}
+
+fn saved_ago(timestamp: u64) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    match now.saturating_sub(timestamp) {
+        _ if timestamp == 0 => String::new(),
+        secs if secs < 60 => ", just now".to_string(),
+        secs if secs < 60 * 60 => format!(", {} min ago", secs / 60),
+        secs if secs < 60 * 60 * 24 => format!(", {} h ago", secs / (60 * 60)),
+        secs => format!(", {} days ago", secs / (60 * 60 * 24)),
+    }
+}
+
+/// The save slots. Left and right switch between practice, which keeps the save around, and
+/// the roguelike default of deleting it once it's loaded.
+pub fn load_menu(
+    gs: &mut State,
+    ctx: &mut Rltk,
+    selection: usize,
+    practice: bool,
+) -> (MenuAction, bool) {
+    let saved = gs.ecs.fetch::<SavedGames>();
+    ctx.print_color_centered(15, COLORS.yellow, COLORS.black, "Load Game");
+
+    let top = 20;
+    for (idx, slot) in saved.slots.iter().enumerate() {
+        let color = if idx == selection {
+            COLORS.magenta
+        } else {
+            COLORS.white
+        };
+        let text = match slot {
+            SlotStatus::Empty => format!("{}. Empty", idx + 1),
+            SlotStatus::Saved(meta) => format!(
+                "{}. {} ({}), depth {}, turn {}{}",
+                idx + 1,
+                meta.name,
+                meta.schools.join("/"),
+                meta.depth,
+                meta.turns,
+                saved_ago(meta.timestamp)
+            ),
+            SlotStatus::Unreadable(_) => format!("{}. Unreadable", idx + 1),
+        };
+        ctx.print_color_centered(top + idx, color, COLORS.black, text);
+    }
+    let mode = if practice {
+        "< Practice: the save stays after loading >"
+    } else {
+        "< Roguelike: the save is deleted once loaded >"
+    };
+    ctx.print_color_centered(top + saved.slots.len() + 1, COLORS.cyan, COLORS.black, mode);
+    draw_menu_notice(&gs.ecs, ctx);
+
+    match ctx.key {
+        None => (MenuAction::NoResponse, practice),
+        Some(key) => match key {
+            _ if key == INPUT.exit => (MenuAction::Cancel, practice),
+            _ if key == INPUT.up && selection > 0 => (MenuAction::Up, practice),
+            _ if key == INPUT.down && selection + 1 < saved.slots.len() => {
+                (MenuAction::Down, practice)
+            }
+            _ if key == INPUT.left || key == INPUT.right => (MenuAction::NoResponse, !practice),
+            _ if key == INPUT.select && saved.slots.get(selection) != Some(&SlotStatus::Empty) => {
+                gs.ecs.write_resource::<MenuNotice>().text = None;
+                (MenuAction::Selected, practice)
+            }
+            _ => (MenuAction::NoResponse, practice),
+        },
+    }
+}
+
+/// Typing in a seed for a new run. Digits build the number up, backspace takes one off.
+pub fn seed_menu(ctx: &mut Rltk, seed: u64) -> (MenuAction, u64) {
+    ctx.print_color_centered(15, COLORS.yellow, COLORS.black, "Maleficer");
+    ctx.print_color_centered(24, COLORS.white, COLORS.black, "Enter a seed:");
+    ctx.print_color_centered(25, COLORS.magenta, COLORS.black, seed.to_string());
+
+    let digit = |key: VirtualKeyCode| match key {
+        VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => Some(0),
+        VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => Some(1),
+        VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => Some(2),
+        VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => Some(3),
+        VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => Some(4),
+        VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => Some(5),
+        VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => Some(6),
+        VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => Some(7),
+        VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => Some(8),
+        VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => Some(9),
+        _ => None,
+    };
+
+    match ctx.key {
+        None => (MenuAction::NoResponse, seed),
+        Some(key) => match key {
+            _ if key == INPUT.exit => (MenuAction::Cancel, seed),
+            _ if key == INPUT.select => (MenuAction::Selected, seed),
+            VirtualKeyCode::Back => (MenuAction::NoResponse, seed / 10),
+            _ => match digit(key) {
+                Some(d) => (
+                    MenuAction::NoResponse,
+                    seed.checked_mul(10)
+                        .and_then(|s| s.checked_add(d))
+                        .unwrap_or(seed),
+                ),
+                None => (MenuAction::NoResponse, seed),
+            },
+        },
+    }
+}
+
 pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
     let runstate = gs.ecs.fetch::<RunState>();
 
@@ -337,7 +866,9 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
 
     let states = [
         MainMenuSelection::NewGame,
+        MainMenuSelection::NewGameWithSeed,
         MainMenuSelection::Continue,
+        MainMenuSelection::WatchReplay,
         MainMenuSelection::Quit,
     ];
 
@@ -350,26 +881,39 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
     } = *runstate
     {
         let mut ngcolor = COLORS.white;
+        let mut sgcolor = COLORS.white;
         let mut lgcolor = COLORS.white;
+        let mut rcolor = COLORS.white;
         let mut qcolor = COLORS.white;
         match selection {
             MainMenuSelection::NewGame => {
                 ngcolor = COLORS.magenta;
                 idx = 0;
             }
+            MainMenuSelection::NewGameWithSeed => {
+                sgcolor = COLORS.magenta;
+                idx = 1;
+            }
             MainMenuSelection::Continue => {
                 lgcolor = COLORS.magenta;
-                idx = 1;
+                idx = 2;
+            }
+            MainMenuSelection::WatchReplay => {
+                rcolor = COLORS.magenta;
+                idx = 3;
             }
             MainMenuSelection::Quit => {
                 qcolor = COLORS.magenta;
-                idx = 2;
+                idx = 4;
             }
         }
 
         ctx.print_color_centered(24, ngcolor, COLORS.black, "Begin New Game");
-        ctx.print_color_centered(25, lgcolor, COLORS.black, "Continue");
-        ctx.print_color_centered(26, qcolor, COLORS.black, "Quit");
+        ctx.print_color_centered(25, sgcolor, COLORS.black, "New Game with Seed");
+        ctx.print_color_centered(26, lgcolor, COLORS.black, "Continue");
+        ctx.print_color_centered(27, rcolor, COLORS.black, "Watch Replay");
+        ctx.print_color_centered(28, qcolor, COLORS.black, "Quit");
+        draw_menu_notice(&gs.ecs, ctx);
 
         match ctx.key {
             None => {
@@ -394,6 +938,7 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
                     };
                 }
                 _ if key == INPUT.select => {
+                    gs.ecs.write_resource::<MenuNotice>().text = None;
                     return MainMenuResult::Selected {
                         selected: selection,
                     }
@@ -10,7 +10,6 @@ pub use map::*;
 mod player;
 pub use player::*;
 mod components;
-pub use components::*;
 mod gamelog;
 mod gui;
 mod rect;
@@ -18,30 +17,61 @@ mod systems;
 pub use gamelog::GameLog;
 mod camera;
 mod config;
+mod describe;
+mod dice;
+mod dungeon;
 pub mod effects;
 pub mod map_builders;
 mod raws;
+mod replay;
+pub use replay::{ActionLog, PlayerInput};
+mod rng;
+pub use rng::RunSeed;
+#[cfg(test)]
+mod sim;
 mod spawner;
 
 #[derive(PartialEq, Copy, Clone)]
 pub enum RunState {
     AwaitingInput,
-    PreRun,
+    PreRun {
+        schools: [usize; 2],
+    },
     CharGen {
         selection: usize,
+        first_pick: Option<usize>,
     },
     PlayerTurn,
-    MonsterTurn,
+    Ticking,
     ShowTargeting {
         range: i32,
         item: Entity,
         radius: i32,
+        throwing: bool,
+    },
+    ShowInventory {
+        selection: usize,
+        sort: gui::InventorySort,
+        inspecting: bool,
     },
     MainMenu {
         game_started: bool,
         menu_selection: gui::MainMenuSelection,
     },
+    Examine,
     NextLevel,
+    PreviousLevel,
+    LevelUp {
+        selection: usize,
+    },
+    EnterSeed {
+        seed: u64,
+    },
+    LoadGame {
+        selection: usize,
+        practice: bool,
+    },
+    GameOver,
 }
 
 pub struct Colors {
@@ -93,15 +123,23 @@ impl GameState for State {
         systems::particle::cull_dead_particles(&mut self.ecs, ctx);
 
         match newrunstate {
-            RunState::MainMenu { .. } => {}
+            RunState::MainMenu { .. } | RunState::EnterSeed { .. } | RunState::LoadGame { .. } => {}
             _ => {
                 camera::render_camera(&self.ecs, ctx);
                 gui::draw_char_ui(&self.ecs, ctx);
                 gui::draw_world_ui(&self.ecs, ctx);
+                if let RunState::AwaitingInput | RunState::Examine = newrunstate {
+                    gui::draw_tooltip(&self.ecs, ctx);
+                }
             }
         }
 
         match newrunstate {
+            RunState::AwaitingInput | RunState::ShowTargeting { .. } | RunState::LevelUp { .. }
+                if replay::is_playing(&self.ecs) =>
+            {
+                newrunstate = self.replay_step(newrunstate, ctx);
+            }
             RunState::MainMenu {
                 game_started,
                 menu_selection: _,
@@ -115,8 +153,26 @@ impl GameState for State {
                         }
                     }
                     gui::MainMenuResult::Selected { selected } => match selected {
+                        // A new run would have nowhere to save
+                        gui::MainMenuSelection::NewGame | gui::MainMenuSelection::NewGameWithSeed
+                            if !systems::save_load::has_free_slot() =>
+                        {
+                            let full = systems::save_load::SaveError::SlotsFull;
+                            self.show_menu_notice(full.to_string());
+                            newrunstate = RunState::MainMenu {
+                                game_started,
+                                menu_selection: selected,
+                            };
+                        }
                         gui::MainMenuSelection::NewGame => {
-                            newrunstate = RunState::CharGen { selection: 0 }
+                            self.ecs.insert(RunSeed::random());
+                            newrunstate = RunState::CharGen {
+                                selection: 0,
+                                first_pick: None,
+                            }
+                        }
+                        gui::MainMenuSelection::NewGameWithSeed => {
+                            newrunstate = RunState::EnterSeed { seed: 0 }
                         }
                         gui::MainMenuSelection::Continue => {
                             let save_exists = systems::save_load::does_save_exist();
@@ -126,124 +182,504 @@ impl GameState for State {
                                 if !save_exists {
                                     // if no save exists, new game
                                     dbg!("save don't exist, making new game");
-                                    newrunstate = RunState::CharGen { selection: 0 };
+                                    self.ecs.insert(RunSeed::random());
+                                    newrunstate = RunState::CharGen {
+                                        selection: 0,
+                                        first_pick: None,
+                                    };
                                 } else {
-                                    // load
-                                    systems::save_load::load_game(&mut self.ecs);
-                                    systems::save_load::delete_save();
+                                    // pick a save to load
+                                    self.ecs.insert(systems::save_load::list_saves());
+                                    newrunstate = RunState::LoadGame {
+                                        selection: 0,
+                                        practice: false,
+                                    };
                                 }
                             }
                         }
+                        gui::MainMenuSelection::WatchReplay => {
+                            if let Some(log) = replay::load_log() {
+                                self.ecs.fetch_mut::<GameLog>().entries.clear();
+                                self.ecs.insert(log.seed);
+                                self.ecs.insert(replay::Playback::new(&log));
+                                newrunstate = RunState::PreRun {
+                                    schools: log.schools,
+                                };
+                            }
+                        }
+                        // Nothing to save before a game has started
+                        gui::MainMenuSelection::Quit if !game_started => ::std::process::exit(0),
                         gui::MainMenuSelection::Quit => {
-                            systems::save_load::save_game(&mut self.ecs);
-                            ::std::process::exit(0);
+                            match systems::save_load::save_game(&mut self.ecs) {
+                                Ok(()) => ::std::process::exit(0),
+                                Err(e) => {
+                                    self.show_menu_notice(e.to_string());
+                                    newrunstate = RunState::MainMenu {
+                                        game_started,
+                                        menu_selection: selected,
+                                    };
+                                }
+                            }
+                        }
+                    },
+                }
+            }
+            RunState::LoadGame {
+                selection,
+                practice,
+            } => {
+                let (menu_result, practice) = gui::load_menu(self, ctx, selection, practice);
+                newrunstate = match menu_result {
+                    gui::MenuAction::Cancel => RunState::MainMenu {
+                        game_started: false,
+                        menu_selection: gui::MainMenuSelection::Continue,
+                    },
+                    gui::MenuAction::Up => RunState::LoadGame {
+                        selection: selection - 1,
+                        practice,
+                    },
+                    gui::MenuAction::Down => RunState::LoadGame {
+                        selection: selection + 1,
+                        practice,
+                    },
+                    gui::MenuAction::Selected => {
+                        match systems::save_load::load_game(&mut self.ecs, selection, practice) {
+                            Ok(()) => RunState::AwaitingInput,
+                            Err(e) => {
+                                self.show_menu_notice(e.to_string());
+                                self.ecs.insert(systems::save_load::list_saves());
+                                RunState::LoadGame {
+                                    selection,
+                                    practice,
+                                }
+                            }
                         }
+                    }
+                    gui::MenuAction::NoResponse => RunState::LoadGame {
+                        selection,
+                        practice,
                     },
+                };
+            }
+            RunState::EnterSeed { seed } => {
+                let (menu_result, typed) = gui::seed_menu(ctx, seed);
+                match menu_result {
+                    gui::MenuAction::Cancel => {
+                        newrunstate = RunState::MainMenu {
+                            game_started: false,
+                            menu_selection: gui::MainMenuSelection::NewGameWithSeed,
+                        }
+                    }
+                    gui::MenuAction::Selected => {
+                        self.ecs.insert(RunSeed::new(typed));
+                        newrunstate = RunState::CharGen {
+                            selection: 0,
+                            first_pick: None,
+                        }
+                    }
+                    _ => newrunstate = RunState::EnterSeed { seed: typed },
                 }
             }
-            RunState::CharGen { selection } => {
+            RunState::CharGen {
+                selection,
+                first_pick,
+            } => {
                 {
                     let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
                     gamelog.entries.clear();
                 }
-                let (menu_result, ch_selection) = gui::chargen_menu(self, ctx, selection);
+                let (menu_result, ch_selection) =
+                    gui::chargen_menu(self, ctx, selection, first_pick);
                 match menu_result {
                     gui::MenuAction::Cancel => {
-                        newrunstate = RunState::MainMenu {
-                            game_started: false,
-                            menu_selection: gui::MainMenuSelection::NewGame,
+                        // Back out of the first pick before leaving the menu
+                        newrunstate = match first_pick {
+                            Some(_) => RunState::CharGen {
+                                selection,
+                                first_pick: None,
+                            },
+                            None => RunState::MainMenu {
+                                game_started: false,
+                                menu_selection: gui::MainMenuSelection::NewGame,
+                            },
                         };
                     }
                     gui::MenuAction::NoResponse => {}
                     gui::MenuAction::Up => {
                         newrunstate = RunState::CharGen {
                             selection: selection - 1,
+                            first_pick,
                         }
                     }
                     gui::MenuAction::Down => {
                         newrunstate = RunState::CharGen {
                             selection: selection + 1,
+                            first_pick,
                         }
                     }
                     gui::MenuAction::Selected => {
-                        println!("selected {}", gui::SCHOOLS[ch_selection.unwrap()]);
-                        newrunstate = RunState::PreRun {};
+                        let picked = ch_selection.unwrap();
+                        newrunstate = match first_pick {
+                            None => RunState::CharGen {
+                                selection,
+                                first_pick: Some(picked),
+                            },
+                            Some(first) if first != picked => RunState::PreRun {
+                                schools: [first, picked],
+                            },
+                            Some(_) => RunState::CharGen {
+                                selection,
+                                first_pick,
+                            },
+                        };
                     }
                 }
             }
-            RunState::PreRun => {
-                self.new_game();
-                player::make_character(&mut self.ecs);
-                self.run_systems();
-                newrunstate = RunState::AwaitingInput;
+            RunState::PreRun { .. }
+            | RunState::PlayerTurn
+            | RunState::Ticking
+            | RunState::NextLevel
+            | RunState::PreviousLevel => {
+                newrunstate = self.advance(newrunstate);
             }
             RunState::AwaitingInput => {
                 self.run_systems();
                 newrunstate = player_input(self, ctx);
             }
-            RunState::PlayerTurn => {
-                self.run_systems();
-                newrunstate = RunState::MonsterTurn;
+            RunState::LevelUp { selection } => {
+                let (menu_result, choice) = gui::level_up_menu(self, ctx, selection);
+                match menu_result {
+                    gui::MenuAction::Up => {
+                        newrunstate = RunState::LevelUp {
+                            selection: selection - 1,
+                        }
+                    }
+                    gui::MenuAction::Down => {
+                        newrunstate = RunState::LevelUp {
+                            selection: selection + 1,
+                        }
+                    }
+                    gui::MenuAction::Selected => {
+                        newrunstate = self.apply_input(
+                            newrunstate,
+                            PlayerInput::LevelUp {
+                                choice: choice.unwrap(),
+                            },
+                        );
+                    }
+                    // Levelling up can't be skipped
+                    gui::MenuAction::Cancel | gui::MenuAction::NoResponse => {}
+                }
             }
-            RunState::MonsterTurn => {
-                let mut mob = systems::monster_ai::MonsterAI {};
-                self.run_systems();
-                mob.run_now(&self.ecs);
-                newrunstate = RunState::AwaitingInput;
+            RunState::ShowInventory {
+                selection,
+                sort,
+                inspecting,
+            } => {
+                let result = gui::inventory_menu(&self.ecs, ctx, selection, sort, inspecting);
+                newrunstate = match result {
+                    gui::InventoryAction::Cancel => RunState::AwaitingInput,
+                    gui::InventoryAction::NoResponse => newrunstate,
+                    gui::InventoryAction::Up => RunState::ShowInventory {
+                        selection: selection - 1,
+                        sort,
+                        inspecting,
+                    },
+                    gui::InventoryAction::Down => RunState::ShowInventory {
+                        selection: selection + 1,
+                        sort,
+                        inspecting,
+                    },
+                    gui::InventoryAction::Sort { sort } => RunState::ShowInventory {
+                        selection: 0,
+                        sort,
+                        inspecting,
+                    },
+                    gui::InventoryAction::Inspect => RunState::ShowInventory {
+                        selection,
+                        sort,
+                        inspecting: !inspecting,
+                    },
+                    // The inventory only sits over the game while it waits on the player
+                    gui::InventoryAction::Act(action) => {
+                        self.apply_input(RunState::AwaitingInput, PlayerInput::Act(action))
+                    }
+                };
             }
-            RunState::NextLevel => {
-                self.goto_next_level();
-                self.run_systems();
-                newrunstate = RunState::AwaitingInput;
+            RunState::GameOver => self.game_over(),
+            RunState::Examine => {
+                if gui::examine(&mut self.ecs, ctx) == gui::MenuAction::Cancel {
+                    newrunstate = RunState::AwaitingInput;
+                }
             }
-            RunState::ShowTargeting {
-                range,
-                item,
-                radius,
-            } => {
+            RunState::ShowTargeting { range, radius, .. } => {
                 let result = gui::ranged_target(&mut self.ecs, ctx, range, radius);
                 match result {
-                    gui::MenuAction::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::MenuAction::Cancel => {
+                        newrunstate = self.apply_input(newrunstate, PlayerInput::CancelTarget)
+                    }
                     gui::MenuAction::Selected => {
-                        let mut intent = self.ecs.write_storage::<WantsToUseItem>();
-                        let cursor = self.ecs.fetch::<Cursor>();
+                        let cursor = self.ecs.fetch::<Cursor>().point;
                         // TODO: should screen_to_tile be an impl in cursor?
-                        let target = camera::screen_to_tile(&self.ecs, cursor.point);
-                        intent
-                            .insert(
-                                *self.ecs.fetch::<Entity>(),
-                                WantsToUseItem {
-                                    item,
-                                    target: Some(target),
-                                },
-                            )
-                            .expect("Unable to insert intent");
-                        newrunstate = RunState::PlayerTurn;
+                        let target = camera::screen_to_tile(&self.ecs, cursor);
+                        newrunstate = self.apply_input(
+                            newrunstate,
+                            PlayerInput::Target {
+                                x: target.x,
+                                y: target.y,
+                            },
+                        );
                     }
                     _ => {}
                 }
             }
         }
+        newrunstate = self.end_frame(newrunstate);
         {
             let mut runwriter = self.ecs.write_resource::<RunState>();
             *runwriter = newrunstate;
         }
-        systems::damage::delete_the_dead(&mut self.ecs);
     }
 }
 
 impl State {
+    /// The run is over. There's no coming back from death, so the save goes and the replay stays.
+    fn game_over(&mut self) {
+        rltk::console::log("You are dead");
+        if let Err(e) = systems::save_load::delete_active_save(&self.ecs) {
+            rltk::console::log(format!("WARNING - Unable to delete the save: {}", e));
+        }
+        replay::save_log(&self.ecs.fetch::<ActionLog>());
+        ::std::process::exit(0);
+    }
+
+    /// What every frame ends with: the dead are cleared away, and a level up waiting to be
+    /// picked comes before the next input
+    fn end_frame(&mut self, runstate: RunState) -> RunState {
+        if systems::damage::delete_the_dead(&mut self.ecs) {
+            return RunState::GameOver;
+        }
+        if runstate == RunState::AwaitingInput && player::has_pending_level_up(&self.ecs) {
+            return RunState::LevelUp { selection: 0 };
+        }
+        runstate
+    }
+
+    /// Runs the states that don't wait on the player, returning where the game goes next
+    fn advance(&mut self, runstate: RunState) -> RunState {
+        match runstate {
+            RunState::PreRun { schools } => {
+                self.new_game();
+                let seed = *self.ecs.fetch::<RunSeed>();
+                self.ecs.insert(ActionLog::new(seed, schools));
+                self.ecs.insert(systems::initiative::TurnCounter::default());
+                self.ecs.insert(systems::save_load::ActiveSave::default());
+                player::make_character(&mut self.ecs, &schools);
+                self.run_systems();
+                RunState::AwaitingInput
+            }
+            RunState::PlayerTurn => {
+                self.run_systems();
+                self.end_player_turn();
+                self.ecs.write_resource::<systems::initiative::TurnCounter>().turns += 1;
+                RunState::Ticking
+            }
+            RunState::Ticking => {
+                // Let everyone else act until it's the player's turn again
+                loop {
+                    let mut initiative = systems::initiative::InitiativeSystem {};
+                    initiative.run_now(&self.ecs);
+                    let mut mob = systems::monster_ai::MonsterAI {};
+                    mob.run_now(&self.ecs);
+                    self.run_systems();
+                    if systems::damage::delete_the_dead(&mut self.ecs) {
+                        return RunState::GameOver;
+                    }
+                    if self.end_monster_turns() {
+                        return RunState::AwaitingInput;
+                    }
+                }
+            }
+            RunState::NextLevel => {
+                self.goto_level(1);
+                self.run_systems();
+                RunState::AwaitingInput
+            }
+            RunState::PreviousLevel => {
+                self.goto_level(-1);
+                self.run_systems();
+                RunState::AwaitingInput
+            }
+            _ => runstate,
+        }
+    }
+
+    // Shown under the menus until the player picks something else
+    fn show_menu_notice(&mut self, text: String) {
+        rltk::console::log(format!("WARNING - {}", text));
+        self.ecs.write_resource::<gui::MenuNotice>().text = Some(text);
+    }
+
+    /// Hands one of the player's decisions to whatever the game is waiting on. Every decision
+    /// comes through here, so the run's action log misses nothing.
+    fn apply_input(&mut self, runstate: RunState, input: PlayerInput) -> RunState {
+        if !input.fits(runstate) {
+            rltk::console::log(format!(
+                "WARNING - {:?} doesn't fit what the game is waiting for",
+                input
+            ));
+            return runstate;
+        }
+        match (runstate, input) {
+            (_, PlayerInput::Act(action)) => {
+                replay::record(&mut self.ecs, input);
+                // Whatever a stunned player tries, the turn goes by without it
+                if action.takes_turn() && player_skips_turn(&mut self.ecs) {
+                    return RunState::PlayerTurn;
+                }
+                perform_action(&mut self.ecs, action)
+            }
+            (
+                RunState::ShowTargeting {
+                    range,
+                    item,
+                    throwing,
+                    ..
+                },
+                PlayerInput::Target { x, y },
+            ) => {
+                let target = Point::new(x, y);
+                if !self.can_target(range, target) {
+                    return runstate;
+                }
+                replay::record(&mut self.ecs, input);
+                if throwing {
+                    return self.throw_at(item, target);
+                }
+                self.select_target(item, target)
+            }
+            (_, PlayerInput::LevelUp { choice }) => {
+                let choices = player::level_up_choices(&self.ecs);
+                if let Some(choice) = choices.get(choice) {
+                    replay::record(&mut self.ecs, input);
+                    player::apply_level_up(&mut self.ecs, choice);
+                    return RunState::AwaitingInput;
+                }
+                runstate
+            }
+            _ => {
+                replay::record(&mut self.ecs, input);
+                RunState::AwaitingInput
+            }
+        }
+    }
+
+    /// Whether a tile is one the targeting screen would let the player pick
+    fn can_target(&self, range: i32, target: Point) -> bool {
+        let player_entity = self.ecs.fetch::<Entity>();
+        let player_pos = self.ecs.fetch::<Point>();
+        let viewsheds = self.ecs.read_storage::<Viewshed>();
+        match viewsheds.get(*player_entity) {
+            Some(viewshed) => {
+                viewshed.visible_tiles.contains(&target)
+                    && rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, target) <= range as f32
+            }
+            None => false,
+        }
+    }
+
+    /// Feeds the game the next recorded input, leaving some time between them to watch
+    fn replay_step(&mut self, runstate: RunState, ctx: &Rltk) -> RunState {
+        if ctx.key == Some(config::INPUT.exit) {
+            self.ecs.write_resource::<replay::Playback>().inputs.clear();
+            let mut gamelog = self.ecs.fetch_mut::<GameLog>();
+            gamelog.entries.push("You take over from the replay.".to_string());
+            return runstate;
+        }
+        if runstate == RunState::AwaitingInput {
+            self.run_systems();
+        }
+        let input = {
+            let mut playback = self.ecs.write_resource::<replay::Playback>();
+            playback.wait_ms -= ctx.frame_time_ms;
+            if playback.wait_ms > 0.0 {
+                return runstate;
+            }
+            playback.wait_ms = replay::REPLAY_STEP_MS;
+            playback.inputs.pop_front()
+        };
+        let newrunstate = match input {
+            Some(input) => self.apply_input(runstate, input),
+            None => runstate,
+        };
+        if !replay::is_playing(&self.ecs) {
+            let mut gamelog = self.ecs.fetch_mut::<GameLog>();
+            gamelog.entries.push("The replay is over.".to_string());
+        }
+        newrunstate
+    }
+
+    /// Aims the spell or item the player was targeting with at a tile
+    fn select_target(&mut self, item: Entity, target: Point) -> RunState {
+        let player_entity = *self.ecs.fetch::<Entity>();
+        if self.ecs.read_storage::<Spell>().get(item).is_some() {
+            let mut intent = self.ecs.write_storage::<WantsToCastSpell>();
+            intent
+                .insert(
+                    player_entity,
+                    WantsToCastSpell {
+                        spell: item,
+                        target: Some(target),
+                    },
+                )
+                .expect("Unable to insert intent");
+        } else {
+            let mut intent = self.ecs.write_storage::<WantsToUseItem>();
+            intent
+                .insert(
+                    player_entity,
+                    WantsToUseItem {
+                        item,
+                        target: Some(target),
+                    },
+                )
+                .expect("Unable to insert intent");
+        }
+        RunState::PlayerTurn
+    }
+
+    fn throw_at(&mut self, item: Entity, target: Point) -> RunState {
+        let player_entity = *self.ecs.fetch::<Entity>();
+        self.ecs
+            .write_storage::<WantsToThrowItem>()
+            .insert(player_entity, WantsToThrowItem { item, target })
+            .expect("Unable to insert intent");
+        RunState::PlayerTurn
+    }
+
     fn run_systems(&mut self) {
+        let mut movement = systems::movement::Movement {};
+        movement.run_now(&self.ecs);
         let mut vis = systems::visibility::Visibility {};
         vis.run_now(&self.ecs);
         let mut mapindex = systems::map_indexing::MapIndexing {};
         mapindex.run_now(&self.ecs);
         let mut melee = systems::melee_combat::MeleeCombat {};
         melee.run_now(&self.ecs);
+        let mut ranged = systems::ranged_combat::RangedCombat {};
+        ranged.run_now(&self.ecs);
         let mut pickup = systems::item::ItemCollection {};
         pickup.run_now(&self.ecs);
         let mut items = systems::item::ItemUse {};
         items.run_now(&self.ecs);
+        let mut drops = systems::item::ItemDrop {};
+        drops.run_now(&self.ecs);
+        let mut throws = systems::item::ItemThrow {};
+        throws.run_now(&self.ecs);
+        let mut spells = systems::spell::SpellCast {};
+        spells.run_now(&self.ecs);
+        let mut status = systems::status::StatusTick {};
+        status.run_now(&self.ecs);
         let mut particles = systems::particle::ParticleSpawn {};
         particles.run_now(&self.ecs);
         effects::run_effects_queue(&mut self.ecs);
@@ -251,20 +687,31 @@ impl State {
         self.ecs.maintain();
     }
 
-    fn new_game(&mut self) {
-        self.ecs.delete_all();
+    fn end_player_turn(&mut self) {
+        let player_entity = *self.ecs.fetch::<Entity>();
+        self.ecs.write_storage::<MyTurn>().remove(player_entity);
+    }
 
-        let mut builder = map_builders::random_builder(1, 100, 100);
-        builder.build_map();
-        {
-            let mut worldmap_resource = self.ecs.write_resource::<Map>();
-            *worldmap_resource = builder.get_map();
+    /// Clears every non-player MyTurn, returning whether the player is up next
+    fn end_monster_turns(&mut self) -> bool {
+        let player_entity = *self.ecs.fetch::<Entity>();
+        let mut turns = self.ecs.write_storage::<MyTurn>();
+        let player_turn = turns.get(player_entity).is_some();
+        turns.clear();
+        if player_turn {
+            turns
+                .insert(player_entity, MyTurn {})
+                .expect("Unable to insert turn");
         }
-        let start = builder.get_starting_position();
+        player_turn
+    }
 
-        let (player_x, player_y) = (start.x, start.y);
+    fn new_game(&mut self) {
+        self.ecs.delete_all();
+        self.ecs.insert(dungeon::MasterDungeonMap::default());
 
-        builder.spawn_entities(&mut self.ecs);
+        let start = self.build_level(1);
+        let (player_x, player_y) = (start.x, start.y);
 
         // TODO: consider making this its own function?
         let player_entity = spawner::player(&mut self.ecs, player_x, player_y);
@@ -277,61 +724,153 @@ impl State {
         self.ecs.insert(Point::new(player_x, player_y));
     }
 
-    // TODO: we would have to edit this every time we add a player-thing.
-    // better to instead remove mobs, map, uncollected items
-    fn entities_to_remove_on_level_change(&mut self) -> Vec<Entity> {
+    /// Builds and fills the level at the given depth from the run's seed, returning where the
+    /// player starts on it
+    fn build_level(&mut self, depth: i32) -> Position {
+        let seed = *self.ecs.fetch::<RunSeed>();
+        let mut rng = seed.level_rng(depth);
+        let mut builder = map_builders::random_builder(depth, 100, 100, &mut rng);
+        builder.build_map(&mut rng);
+        let start = builder.get_starting_position();
+        {
+            let mut worldmap_resource = self.ecs.write_resource::<Map>();
+            *worldmap_resource = builder.get_map();
+            // Every floor below the first leads back up from where the player arrives
+            if depth > 1 {
+                let idx = worldmap_resource.xy_idx(start.x, start.y);
+                worldmap_resource.tiles[idx] = TileType::UpStairs;
+            }
+        }
+
+        // The rest of the level draws from the same stream the map was built with
+        self.ecs.insert(rng);
+        builder.spawn_entities(&mut self.ecs);
+        start
+    }
+
+    fn level_entities(&self) -> Vec<Entity> {
         let entities = self.ecs.entities();
-        let player = self.ecs.read_storage::<Player>();
+        let on_level = self.ecs.read_storage::<OnLevel>();
+        (&entities, &on_level).join().map(|(e, _)| e).collect()
+    }
+
+    /// Allies, along with whatever they carry and know
+    fn followers(&self) -> Vec<Entity> {
+        let entities = self.ecs.entities();
+        let factions = self.ecs.read_storage::<Faction>();
+        let status_effects = self.ecs.read_storage::<StatusEffects>();
         let backpack = self.ecs.read_storage::<InBackpack>();
-        let player_entity = self.ecs.fetch::<Entity>();
+        let known_spells = self.ecs.read_storage::<KnownSpell>();
 
-        let mut to_delete: Vec<Entity> = Vec::new();
-        for entity in entities.join() {
-            let mut should_delete = true;
+        let mut followers: Vec<Entity> = (&entities, &factions)
+            .join()
+            .filter(|(e, f)| f.allegiance(status_effects.get(*e)) == raws::ALLY_FACTION)
+            .map(|(e, _)| e)
+            .collect();
+        let carried = (&entities, &backpack)
+            .join()
+            .filter(|(_, pack)| followers.contains(&pack.owner))
+            .map(|(e, _)| e);
+        let known = (&entities, &known_spells)
+            .join()
+            .filter(|(_, known)| followers.contains(&known.owner))
+            .map(|(e, _)| e);
+        let belongings: Vec<Entity> = carried.chain(known).collect();
+        followers.extend(belongings);
+        followers
+    }
 
-            // Don't delete the player
-            let p = player.get(entity);
-            if let Some(_p) = p {
-                should_delete = false;
-            }
+    /// Sets followers down on the new level, as close to the player as there's room for
+    fn bring_followers(&mut self, followers: &[Entity], near: &Position) {
+        let map = self.ecs.fetch::<Map>();
+        let entities = self.ecs.entities();
+        let mut positions = self.ecs.write_storage::<Position>();
+        let blockers = self.ecs.read_storage::<BlocksTile>();
+        let mut on_level = self.ecs.write_storage::<OnLevel>();
+        let mut viewsheds = self.ecs.write_storage::<Viewshed>();
+        let mut behaviours = self.ecs.write_storage::<Behaviour>();
 
-            // Don't delete the player's equipment
-            let bp = backpack.get(entity);
-            if let Some(bp) = bp {
-                if bp.owner == *player_entity {
-                    should_delete = false;
-                }
-            }
+        let mut taken: Vec<Point> = (&entities, &positions, &blockers)
+            .join()
+            .filter(|(e, _, _)| !followers.contains(e))
+            .map(|(_, pos, _)| Point::new(pos.x, pos.y))
+            .collect();
+        taken.push(Point::new(near.x, near.y));
+        let mut open: Vec<Point> = (0..map.tile_count)
+            .filter(|idx| map.tiles[*idx] != TileType::Wall)
+            .map(|idx| {
+                let (x, y) = map.idx_xy(idx as i32);
+                Point::new(x, y)
+            })
+            .filter(|pt| !taken.contains(pt))
+            .collect();
+        let start = Point::new(near.x, near.y);
+        let distance = |pt: &Point| rltk::DistanceAlg::Pythagoras.distance2d(start, *pt);
+        open.sort_by(|a, b| distance(b).partial_cmp(&distance(a)).unwrap());
 
-            if should_delete {
-                to_delete.push(entity);
+        for follower in followers {
+            on_level
+                .insert(*follower, OnLevel {})
+                .expect("Unable to insert level");
+            // Carried things and spells go along without a spot of their own
+            let Some(pos) = positions.get_mut(*follower) else {
+                continue;
+            };
+            let Some(spot) = open.pop() else {
+                continue;
+            };
+            pos.x = spot.x;
+            pos.y = spot.y;
+            if let Some(viewshed) = viewsheds.get_mut(*follower) {
+                viewshed.dirty = true;
+            }
+            if let Some(behaviour) = behaviours.get_mut(*follower) {
+                behaviour.state = AiState::Idle;
+                behaviour.last_seen = None;
+                behaviour.home = spot;
             }
         }
-        to_delete
     }
-    // TODO: understand this
-    fn goto_next_level(&mut self) {
-        // Delete entities that aren't the player or his/her equipment
-        let to_delete = self.entities_to_remove_on_level_change();
-        for target in to_delete {
-            self.ecs
-                .delete_entity(target)
-                .expect("Unable to delete entity");
-        }
 
-        // Build a new map and place the player
-        let mut builder;
-        let player_start;
+    /// Takes the player `offset` floors down, or up when it's negative. Floors they've left
+    /// before come back the way they were. Allies come too.
+    fn goto_level(&mut self, offset: i32) {
+        let followers = self.followers();
         {
-            let mut worldmap_resource = self.ecs.write_resource::<Map>();
-            let current_depth = worldmap_resource.depth;
-            builder = map_builders::random_builder(current_depth + 1, 100, 100);
-            builder.build_map();
-            *worldmap_resource = builder.get_map();
-            player_start = builder.get_starting_position();
+            let mut on_level = self.ecs.write_storage::<OnLevel>();
+            for follower in followers.iter() {
+                on_level.remove(*follower);
+            }
+        }
+        let to_stash = self.level_entities();
+        if let Err(e) = dungeon::stash_level(&mut self.ecs, &to_stash) {
+            rltk::console::log(format!("WARNING - Unable to store the level: {}", e));
         }
 
-        builder.spawn_entities(&mut self.ecs);
+        let depth = self.ecs.fetch::<Map>().depth + offset;
+        let restored = dungeon::restore_level(&mut self.ecs, depth).unwrap_or_else(|e| {
+            rltk::console::log(format!("WARNING - Unable to restore the level: {}", e));
+            false
+        });
+        let player_start = if restored {
+            // Come out on the stairs leading back to where the player just was
+            let stairs = if offset > 0 {
+                TileType::UpStairs
+            } else {
+                TileType::DownStairs
+            };
+            let map = self.ecs.fetch::<Map>();
+            let idx = map
+                .tiles
+                .iter()
+                .position(|tile| *tile == stairs)
+                .expect("A stored level lost its stairs");
+            let (x, y) = map.idx_xy(idx as i32);
+            Position { x, y }
+        } else {
+            self.build_level(depth)
+        };
+        self.bring_followers(&followers, &player_start);
 
         // Place the player and update resources
         let (player_x, player_y) = (player_start.x, player_start.y);
@@ -353,8 +892,15 @@ impl State {
             vs.dirty = true;
         }
 
-        // Notify the player and give them some health
         let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
+        if restored {
+            gamelog
+                .entries
+                .push(format!("You return to level {}.", depth));
+            return;
+        }
+
+        // Notify the player and give them some health
         gamelog
             .entries
             .push("You descend to the next level, and take a moment to heal.".to_string());
@@ -369,45 +915,24 @@ impl State {
 }
 
 fn register_all(gs: &mut State) {
-    gs.ecs.register::<Position>();
-    gs.ecs.register::<Renderable>();
-    gs.ecs.register::<Player>();
-    gs.ecs.register::<Viewshed>();
-    gs.ecs.register::<Monster>();
-    gs.ecs.register::<Name>();
-    gs.ecs.register::<BlocksTile>();
-    gs.ecs.register::<EntityStats>();
-    gs.ecs.register::<WantsToMelee>();
-    gs.ecs.register::<Item>();
-    gs.ecs.register::<ProvidesHealing>();
-    gs.ecs.register::<ProvidesMana>();
-    gs.ecs.register::<CostsMana>();
-    gs.ecs.register::<InBackpack>();
-    gs.ecs.register::<WantsToPickupItem>();
-    gs.ecs.register::<WantsToDropItem>();
-    gs.ecs.register::<WantsToUseItem>();
-    gs.ecs.register::<Consumable>();
-    gs.ecs.register::<Ranged>();
-    gs.ecs.register::<InflictsDamage>();
-    gs.ecs.register::<AreaOfEffect>();
+    // Everything worth registering is worth saving, so the save's list is the one to use
+    systems::save_load::register_saved_components(&mut gs.ecs);
     gs.ecs.register::<SimpleMarker<SerializeMe>>();
-    gs.ecs.register::<SerializationHelper>();
-    gs.ecs.register::<SerializationHelper>();
-    gs.ecs.register::<Cursor>();
-    gs.ecs.register::<Spell>();
-    gs.ecs.register::<WantsToCastSpell>();
-    gs.ecs.register::<ParticleLifetime>();
-    gs.ecs.register::<SpawnParticleLine>();
-    gs.ecs.register::<SpawnParticleBurst>();
-    gs.ecs.register::<Hidden>();
-    gs.ecs.register::<Antagonistic>();
-    gs.ecs.register::<SingleActivation>();
-    gs.ecs.register::<TeleportTo>();
-    gs.ecs.register::<ApplyTeleport>();
 
     // gs.ecs.insert(TeleportTo{x:0,y:0});
     gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
     gs.ecs.insert(rltk::RandomNumberGenerator::new());
+    let seed = RunSeed::random();
+    gs.ecs.insert(seed);
+    // Replaced as soon as a run starts
+    gs.ecs.insert(ActionLog::new(seed, [0, 1]));
+    gs.ecs.insert(replay::Playback::default());
+    gs.ecs.insert(gui::MenuNotice::default());
+    gs.ecs.insert(effects::EffectQueue::default());
+    gs.ecs.insert(systems::initiative::TurnCounter::default());
+    gs.ecs.insert(systems::save_load::ActiveSave::default());
+    gs.ecs.insert(systems::save_load::SavedGames::default());
+    gs.ecs.insert(dungeon::MasterDungeonMap::default());
 
     let player_entity = spawner::player(&mut gs.ecs, 0, 0);
     gs.ecs.insert(player_entity);
@@ -454,6 +979,7 @@ fn main() -> rltk::BError {
 
 #[cfg(test)]
 mod tests {
+    use crate::sim::Simulation;
     use crate::*;
 
     #[test]
@@ -461,4 +987,83 @@ mod tests {
         let mut test_state = State { ecs: World::new() };
         register_all(&mut test_state);
     }
+
+    #[test]
+    fn test_look_starts_on_player() {
+        let mut sim = Simulation::new(7, ["Pyromancy", "Cryomancy"]);
+        let runstate = sim.input(PlayerInput::Act(PlayerAction::Look));
+        assert!(runstate == RunState::Examine);
+
+        let cursor = sim.state.ecs.fetch::<Cursor>().point;
+        let looking_at = camera::screen_to_tile(&sim.state.ecs, cursor);
+        assert_eq!(looking_at, sim.player_pos());
+        assert!(sim.state.ecs.fetch::<ActionLog>().inputs.is_empty());
+    }
+
+    #[test]
+    fn test_descend() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let pos = sim.player_pos();
+        {
+            let mut map = sim.ecs().write_resource::<Map>();
+            let idx = map.xy_idx(pos.x, pos.y);
+            map.tiles[idx] = TileType::DownStairs;
+        }
+
+        let runstate = sim.input(PlayerInput::Act(PlayerAction::Descend));
+
+        assert!(runstate == RunState::AwaitingInput);
+        let pos = sim.player_pos();
+        let map = sim.state.ecs.fetch::<Map>();
+        assert_eq!(map.depth, 2);
+        assert!(map.tiles[map.xy_idx(pos.x, pos.y)] == TileType::UpStairs);
+    }
+
+    #[test]
+    fn test_levels_persist() {
+        let mut sim = Simulation::quiet(["Evocation", "Pyromancy"]);
+        let pos = sim.player_pos();
+        let potion = sim.spawn("Health Potion", pos.x, pos.y);
+        sim.input(PlayerInput::Act(PlayerAction::PickUp));
+        let goblin = sim.spawn("Goblin", pos.x + 3, pos.y);
+        let spells = sim.ecs().read_storage::<Spell>().join().count();
+        let tiles = sim.state.ecs.fetch::<Map>().tiles.clone();
+        let stairs = sim.stand_on(TileType::DownStairs);
+
+        sim.input(PlayerInput::Act(PlayerAction::Descend));
+        assert!(!sim.ecs().is_alive(goblin));
+        assert!(sim.ecs().is_alive(potion));
+        assert_eq!(sim.ecs().read_storage::<Spell>().join().count(), spells);
+        let below = sim.state.ecs.fetch::<Map>().tiles.clone();
+        sim.input(PlayerInput::Act(PlayerAction::Ascend));
+
+        assert_eq!(sim.state.ecs.fetch::<Map>().depth, 1);
+        assert!(sim.state.ecs.fetch::<Map>().tiles == tiles);
+        assert_eq!(sim.player_pos(), stairs);
+        let goblin = sim.named("Goblin").expect("The goblin wasn't brought back");
+        assert_eq!(sim.position(goblin), Some(Point::new(pos.x + 3, pos.y)));
+
+        sim.input(PlayerInput::Act(PlayerAction::Descend));
+        assert!(sim.state.ecs.fetch::<Map>().tiles == below);
+        assert!(sim.log().last().unwrap() == "You return to level 2.");
+    }
+
+    #[test]
+    fn test_allies_follow_between_levels() {
+        let mut sim = Simulation::quiet(["Conjuration", "Pyromancy"]);
+        let pos = sim.player_pos();
+        sim.cast_at("Summon Wolf", pos.x + 1, pos.y);
+        let wolf = sim.named("Wolf").unwrap();
+
+        sim.stand_on(TileType::DownStairs);
+        sim.input(PlayerInput::Act(PlayerAction::Descend));
+        assert_eq!(sim.ecs().fetch::<Map>().depth, 2);
+        assert!(sim.ecs().is_alive(wolf));
+        assert!(sim.ecs().read_storage::<OnLevel>().get(wolf).is_some());
+        assert!(sim.distance_to_player(wolf) < 3.0);
+
+        sim.input(PlayerInput::Act(PlayerAction::Ascend));
+        assert!(sim.ecs().is_alive(wolf));
+        assert!(sim.distance_to_player(wolf) < 3.0);
+    }
 }
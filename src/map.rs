@@ -7,11 +7,12 @@ use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 
 // TODO: things in crossterm update slowly, only when I spam keys
-#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum TileType {
     Wall,
     Floor,
     DownStairs,
+    UpStairs,
 }
 
 #[derive(Default, Serialize, Deserialize, Clone)]
@@ -37,7 +38,6 @@ impl Map {
     }
 
     /// for DRY reasons. TODO: let it take i32s and actually use the func
-
     pub fn idx_xy(&self, idx: i32) -> (i32, i32) {
         let id = idx;
         let x = id % self.width;
@@ -83,18 +83,27 @@ impl Map {
     }
 }
 
-// TODO: move out of this file
-pub fn try_next_level(ecs: &mut World) -> bool {
+pub fn player_tile(ecs: &World) -> TileType {
     let player_pos = ecs.fetch::<Point>();
     let map = ecs.fetch::<Map>();
-    let player_idx = map.xy_idx(player_pos.x, player_pos.y);
-    if map.tiles[player_idx] == TileType::DownStairs {
+    map.tiles[map.xy_idx(player_pos.x, player_pos.y)]
+}
+
+// TODO: move out of this file
+pub fn try_next_level(ecs: &mut World) -> bool {
+    try_stairs(ecs, TileType::DownStairs, "There is no way down from here.")
+}
+
+pub fn try_previous_level(ecs: &mut World) -> bool {
+    try_stairs(ecs, TileType::UpStairs, "There is no way up from here.")
+}
+
+fn try_stairs(ecs: &mut World, stairs: TileType, refusal: &str) -> bool {
+    if player_tile(ecs) == stairs {
         true
     } else {
         let mut gamelog = ecs.fetch_mut::<GameLog>();
-        gamelog
-            .entries
-            .push("There is no way down from here.".to_string());
+        gamelog.entries.push(refusal.to_string());
         false
     }
 }
@@ -0,0 +1,93 @@
+use crate::{Position, TileType};
+
+use super::common::*;
+use super::{BuilderMap, MetaMapBuilder};
+use rltk::{Point, RandomNumberGenerator};
+
+fn start_idx(build_data: &BuilderMap) -> usize {
+    match &build_data.starting_position {
+        Some(pos) => build_data.map.xy_idx(pos.x, pos.y),
+        None => panic!("Area based builders need a starting position first"),
+    }
+}
+
+/// Starts the player as close to the middle of the map as the largest open region allows
+pub struct AreaStartingPosition {}
+
+impl MetaMapBuilder for AreaStartingPosition {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let map = &build_data.map;
+        let centre = Point::new(map.width / 2, map.height / 2);
+        let closest = largest_open_region(map)
+            .into_iter()
+            .map(|idx| {
+                let (x, y) = map.idx_xy(idx as i32);
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(centre, Point::new(x, y));
+                (x, y, distance)
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+        if let Some((x, y, _)) = closest {
+            build_data.starting_position = Some(Position { x, y });
+        }
+    }
+}
+
+impl AreaStartingPosition {
+    pub fn new() -> AreaStartingPosition {
+        AreaStartingPosition {}
+    }
+}
+
+/// Walls off anything the player can't walk to
+pub struct CullUnreachable {}
+
+impl MetaMapBuilder for CullUnreachable {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let start_idx = start_idx(build_data);
+        remove_unreachable_areas_returning_most_distant(&mut build_data.map, start_idx);
+    }
+}
+
+impl CullUnreachable {
+    pub fn new() -> CullUnreachable {
+        CullUnreachable {}
+    }
+}
+
+/// Puts the way down as far from the start as the map allows
+pub struct DistantExit {}
+
+impl MetaMapBuilder for DistantExit {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let start_idx = start_idx(build_data);
+        let exit_idx = remove_unreachable_areas_returning_most_distant(&mut build_data.map, start_idx);
+        build_data.map.tiles[exit_idx] = TileType::DownStairs;
+    }
+}
+
+impl DistantExit {
+    pub fn new() -> DistantExit {
+        DistantExit {}
+    }
+}
+
+/// Spawns into cellular noise regions, skipping the one the player starts in
+pub struct VoronoiSpawning {}
+
+impl MetaMapBuilder for VoronoiSpawning {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let start_idx = start_idx(build_data);
+        let noise_areas = generate_voronoi_spawn_regions(&build_data.map, rng);
+        build_data.spawn_areas.extend(
+            noise_areas
+                .into_values()
+                .filter(|area| !area.contains(&start_idx)),
+        );
+    }
+}
+
+impl VoronoiSpawning {
+    pub fn new() -> VoronoiSpawning {
+        VoronoiSpawning {}
+    }
+}
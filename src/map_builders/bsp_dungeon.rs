@@ -0,0 +1,129 @@
+use crate::rect::Rect;
+use crate::TileType;
+
+use super::common::*;
+use super::Map;
+use super::{BuilderMap, InitialMapBuilder};
+use rltk::RandomNumberGenerator;
+
+pub struct BspDungeonBuilder {
+    rects: Vec<Rect>,
+}
+
+impl InitialMapBuilder for BspDungeonBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        self.build(rng, build_data);
+    }
+}
+
+impl BspDungeonBuilder {
+    pub fn new() -> BspDungeonBuilder {
+        BspDungeonBuilder { rects: Vec::new() }
+    }
+
+    /// Binary space partitioning: keep splitting the map into quarters, and carve a room into
+    /// whichever pieces have space for one. Rooms come out sorted left to right.
+    fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        const MAX_ATTEMPTS: i32 = 240;
+
+        let mut rooms: Vec<Rect> = Vec::new();
+
+        self.rects.clear();
+        self.rects.push(Rect::new(
+            2,
+            2,
+            build_data.map.width - 5,
+            build_data.map.height - 5,
+        ));
+        let first_room = self.rects[0];
+        self.add_subrects(first_room);
+
+        for _ in 0..MAX_ATTEMPTS {
+            let rect = self.get_random_rect(rng);
+            let candidate = self.get_random_sub_rect(rect, rng);
+
+            if self.is_possible(&build_data.map, candidate) {
+                apply_room_to_map(&mut build_data.map, &candidate);
+                rooms.push(candidate);
+                self.add_subrects(rect);
+            }
+        }
+
+        rooms.sort_by_key(|room| room.x1);
+        build_data.rooms = Some(rooms);
+    }
+
+    fn add_subrects(&mut self, rect: Rect) {
+        let width = i32::abs(rect.x1 - rect.x2);
+        let height = i32::abs(rect.y1 - rect.y2);
+        let half_width = i32::max(width / 2, 1);
+        let half_height = i32::max(height / 2, 1);
+
+        self.rects
+            .push(Rect::new(rect.x1, rect.y1, half_width, half_height));
+        self.rects.push(Rect::new(
+            rect.x1,
+            rect.y1 + half_height,
+            half_width,
+            half_height,
+        ));
+        self.rects.push(Rect::new(
+            rect.x1 + half_width,
+            rect.y1,
+            half_width,
+            half_height,
+        ));
+        self.rects.push(Rect::new(
+            rect.x1 + half_width,
+            rect.y1 + half_height,
+            half_width,
+            half_height,
+        ));
+    }
+
+    fn get_random_rect(&mut self, rng: &mut RandomNumberGenerator) -> Rect {
+        if self.rects.len() == 1 {
+            return self.rects[0];
+        }
+        let idx = (rng.roll_dice(1, self.rects.len() as i32) - 1) as usize;
+        self.rects[idx]
+    }
+
+    fn get_random_sub_rect(&self, rect: Rect, rng: &mut RandomNumberGenerator) -> Rect {
+        let mut result = rect;
+        let rect_width = i32::abs(rect.x1 - rect.x2);
+        let rect_height = i32::abs(rect.y1 - rect.y2);
+
+        let w = i32::max(3, rng.roll_dice(1, i32::min(rect_width, 10)) - 1) + 1;
+        let h = i32::max(3, rng.roll_dice(1, i32::min(rect_height, 10)) - 1) + 1;
+
+        result.x1 += rng.roll_dice(1, 6) - 1;
+        result.y1 += rng.roll_dice(1, 6) - 1;
+        result.x2 = result.x1 + w;
+        result.y2 = result.y1 + h;
+
+        result
+    }
+
+    // A room fits if it's inside the map and only overlaps solid rock
+    fn is_possible(&self, map: &Map, rect: Rect) -> bool {
+        let mut expanded = rect;
+        expanded.x1 -= 2;
+        expanded.x2 += 2;
+        expanded.y1 -= 2;
+        expanded.y2 += 2;
+
+        for y in expanded.y1..=expanded.y2 {
+            for x in expanded.x1..=expanded.x2 {
+                if x > map.width - 2 || y > map.height - 2 || x < 1 || y < 1 {
+                    return false;
+                }
+                let idx = map.xy_idx(x, y);
+                if map.tiles[idx] != TileType::Wall {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
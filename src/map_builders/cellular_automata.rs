@@ -0,0 +1,66 @@
+use crate::TileType;
+
+use super::{BuilderMap, InitialMapBuilder};
+use rltk::RandomNumberGenerator;
+
+pub struct CellularAutomataBuilder {}
+
+impl InitialMapBuilder for CellularAutomataBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        self.build(rng, build_data);
+    }
+}
+
+impl CellularAutomataBuilder {
+    pub fn new() -> CellularAutomataBuilder {
+        CellularAutomataBuilder {}
+    }
+
+    /// Caves: start from random noise, then repeatedly let each tile become whatever most of
+    /// its neighbours are until the noise settles into open caverns.
+    fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        const ITERATIONS: i32 = 15;
+
+        for y in 1..build_data.map.height - 1 {
+            for x in 1..build_data.map.width - 1 {
+                let idx = build_data.map.xy_idx(x, y);
+                build_data.map.tiles[idx] = if rng.roll_dice(1, 100) > 55 {
+                    TileType::Floor
+                } else {
+                    TileType::Wall
+                };
+            }
+        }
+
+        for _ in 0..ITERATIONS {
+            let mut new_tiles = build_data.map.tiles.clone();
+            for y in 1..build_data.map.height - 1 {
+                for x in 1..build_data.map.width - 1 {
+                    let idx = build_data.map.xy_idx(x, y);
+                    let mut neighbours = 0;
+                    for (dx, dy) in [
+                        (-1, -1),
+                        (0, -1),
+                        (1, -1),
+                        (-1, 0),
+                        (1, 0),
+                        (-1, 1),
+                        (0, 1),
+                        (1, 1),
+                    ] {
+                        let n_idx = build_data.map.xy_idx(x + dx, y + dy);
+                        if build_data.map.tiles[n_idx] == TileType::Wall {
+                            neighbours += 1;
+                        }
+                    }
+                    new_tiles[idx] = if neighbours > 4 || neighbours == 0 {
+                        TileType::Wall
+                    } else {
+                        TileType::Floor
+                    };
+                }
+            }
+            build_data.map.tiles = new_tiles;
+        }
+    }
+}
@@ -2,6 +2,7 @@ use super::Map;
 use crate::map::TileType;
 use crate::rect::Rect;
 use std::cmp::{max, min};
+use std::collections::BTreeMap;
 
 pub fn apply_room_to_map(map: &mut Map, room: &Rect) {
     for y in room.y1 + 1..room.y2 {
@@ -12,6 +13,20 @@ pub fn apply_room_to_map(map: &mut Map, room: &Rect) {
     }
 }
 
+/// Every tile inside a room's walls
+pub fn room_tiles(map: &Map, room: &Rect) -> Vec<usize> {
+    let mut tiles = Vec::new();
+    for y in room.y1 + 1..room.y2 {
+        for x in room.x1 + 1..room.x2 {
+            let idx = map.xy_idx(x, y);
+            if in_bounds(map, idx) {
+                tiles.push(idx);
+            }
+        }
+    }
+    tiles
+}
+
 pub fn in_bounds(map: &Map, idx: usize) -> bool {
     idx > 0 && idx < map.tile_count
 }
@@ -33,3 +48,86 @@ pub fn apply_vertical_tunnel(map: &mut Map, y1: i32, y2: i32, x: i32) {
         }
     }
 }
+
+/// Walls off every floor tile that can't be reached from `start_idx`, and returns the index of
+/// the reachable tile furthest from it.
+pub fn remove_unreachable_areas_returning_most_distant(map: &mut Map, start_idx: usize) -> usize {
+    map.populate_blocked();
+    let dijkstra_map = rltk::DijkstraMap::new(
+        map.width as usize,
+        map.height as usize,
+        &[start_idx],
+        map,
+        1000.0,
+    );
+
+    let mut exit_tile = (start_idx, 0.0f32);
+    for (i, tile) in map.tiles.iter_mut().enumerate() {
+        if *tile == TileType::Floor {
+            let distance_to_start = dijkstra_map.map[i];
+            if distance_to_start == f32::MAX {
+                *tile = TileType::Wall;
+            } else if distance_to_start > exit_tile.1 {
+                exit_tile = (i, distance_to_start);
+            }
+        }
+    }
+    exit_tile.0
+}
+
+/// Splits the floor of a map into cellular noise regions, for layouts that have no rooms to
+/// spawn things in.
+pub fn generate_voronoi_spawn_regions(
+    map: &Map,
+    rng: &mut rltk::RandomNumberGenerator,
+) -> BTreeMap<i32, Vec<usize>> {
+    let mut noise_areas: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+    let mut noise = rltk::FastNoise::seeded(rng.roll_dice(1, 65536) as u64);
+    noise.set_noise_type(rltk::NoiseType::Cellular);
+    noise.set_frequency(0.08);
+    noise.set_cellular_distance_function(rltk::CellularDistanceFunction::Manhattan);
+
+    for y in 1..map.height - 1 {
+        for x in 1..map.width - 1 {
+            let idx = map.xy_idx(x, y);
+            if map.tiles[idx] == TileType::Floor {
+                let cell_value = (noise.get_noise(x as f32, y as f32) * 10240.0) as i32;
+                noise_areas.entry(cell_value).or_default().push(idx);
+            }
+        }
+    }
+    noise_areas
+}
+
+/// The biggest group of floor tiles that can all walk to one another
+pub fn largest_open_region(map: &Map) -> Vec<usize> {
+    let mut seen = vec![false; map.tiles.len()];
+    let mut largest: Vec<usize> = Vec::new();
+
+    for start in 0..map.tiles.len() {
+        if seen[start] || map.tiles[start] == TileType::Wall {
+            continue;
+        }
+        seen[start] = true;
+        let mut region = Vec::new();
+        let mut open = vec![start];
+        while let Some(idx) = open.pop() {
+            region.push(idx);
+            let (x, y) = map.idx_xy(idx as i32);
+            for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                if nx < 0 || ny < 0 || nx >= map.width || ny >= map.height {
+                    continue;
+                }
+                let n_idx = map.xy_idx(nx, ny);
+                if !seen[n_idx] && map.tiles[n_idx] != TileType::Wall {
+                    seen[n_idx] = true;
+                    open.push(n_idx);
+                }
+            }
+        }
+        if region.len() > largest.len() {
+            largest = region;
+        }
+    }
+    largest
+}
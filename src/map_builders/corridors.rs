@@ -0,0 +1,75 @@
+use crate::rect::Rect;
+
+use super::common::*;
+use super::{BuilderMap, MetaMapBuilder};
+use rltk::{Point, RandomNumberGenerator};
+
+fn rooms_of(build_data: &BuilderMap) -> Vec<Rect> {
+    match &build_data.rooms {
+        Some(rooms) => rooms.clone(),
+        None => panic!("Corridors require a builder with room structures"),
+    }
+}
+
+/// Joins each room to the closest room after it in the list, with an L-shaped corridor
+/// between their centres
+pub struct NearestCorridors {}
+
+impl MetaMapBuilder for NearestCorridors {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let rooms = rooms_of(build_data);
+        for i in 0..rooms.len() - 1 {
+            let (x, y) = rooms[i].center();
+            let this_room = Point { x, y };
+            let mut closest_room = Point { x: 0, y: 0 };
+            let mut min_distance = f32::INFINITY;
+            for other in rooms.iter().skip(i + 1) {
+                let (x, y) = other.center();
+                let old_room = Point { x, y };
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(this_room, old_room);
+                if distance < min_distance {
+                    min_distance = distance;
+                    closest_room = old_room;
+                }
+            }
+            let map = &mut build_data.map;
+            if rng.range(0, 2) == 1 {
+                apply_horizontal_tunnel(map, closest_room.x, this_room.x, closest_room.y);
+                apply_vertical_tunnel(map, closest_room.y, this_room.y, this_room.x);
+            } else {
+                apply_vertical_tunnel(map, closest_room.y, this_room.y, closest_room.x);
+                apply_horizontal_tunnel(map, closest_room.x, this_room.x, this_room.y);
+            }
+        }
+    }
+}
+
+impl NearestCorridors {
+    pub fn new() -> NearestCorridors {
+        NearestCorridors {}
+    }
+}
+
+/// Joins each room to the next one in the list, between random points inside them
+pub struct DoglegCorridors {}
+
+impl MetaMapBuilder for DoglegCorridors {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let rooms = rooms_of(build_data);
+        for pair in rooms.windows(2) {
+            let (room, next_room) = (pair[0], pair[1]);
+            let start_x = room.x1 + rng.roll_dice(1, i32::abs(room.x1 - room.x2) - 1);
+            let start_y = room.y1 + rng.roll_dice(1, i32::abs(room.y1 - room.y2) - 1);
+            let end_x = next_room.x1 + rng.roll_dice(1, i32::abs(next_room.x1 - next_room.x2) - 1);
+            let end_y = next_room.y1 + rng.roll_dice(1, i32::abs(next_room.y1 - next_room.y2) - 1);
+            apply_horizontal_tunnel(&mut build_data.map, start_x, end_x, start_y);
+            apply_vertical_tunnel(&mut build_data.map, start_y, end_y, end_x);
+        }
+    }
+}
+
+impl DoglegCorridors {
+    pub fn new() -> DoglegCorridors {
+        DoglegCorridors {}
+    }
+}
@@ -0,0 +1,66 @@
+use crate::TileType;
+
+use super::{BuilderMap, InitialMapBuilder};
+use rltk::RandomNumberGenerator;
+
+pub struct DrunkardsWalkBuilder {}
+
+impl InitialMapBuilder for DrunkardsWalkBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        self.build(rng, build_data);
+    }
+}
+
+impl DrunkardsWalkBuilder {
+    pub fn new() -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder {}
+    }
+
+    /// Sends diggers stumbling about from the middle of the map, and then from already-open
+    /// ground, until enough of it has been dug out. Each digger gives up after a while.
+    fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        const DIGGER_LIFETIME: i32 = 400;
+        const DESIRED_FLOOR_PERCENT: f32 = 0.5;
+
+        let (start_x, start_y) = (build_data.map.width / 2, build_data.map.height / 2);
+        let start_idx = build_data.map.xy_idx(start_x, start_y);
+        build_data.map.tiles[start_idx] = TileType::Floor;
+
+        let total_tiles = (build_data.map.width * build_data.map.height) as f32;
+        let desired_floor_tiles = (DESIRED_FLOOR_PERCENT * total_tiles) as usize;
+        let mut floor_tile_count = 1;
+
+        while floor_tile_count < desired_floor_tiles {
+            // Every digger after the first sets off from a random spot it has already dug
+            let (mut x, mut y) = if floor_tile_count == 1 {
+                (start_x, start_y)
+            } else {
+                let floor: Vec<usize> = build_data
+                    .map
+                    .tiles
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, tile)| **tile == TileType::Floor)
+                    .map(|(idx, _)| idx)
+                    .collect();
+                let idx = floor[(rng.roll_dice(1, floor.len() as i32) - 1) as usize];
+                build_data.map.idx_xy(idx as i32)
+            };
+
+            for _ in 0..DIGGER_LIFETIME {
+                let idx = build_data.map.xy_idx(x, y);
+                if build_data.map.tiles[idx] == TileType::Wall {
+                    build_data.map.tiles[idx] = TileType::Floor;
+                    floor_tile_count += 1;
+                }
+                match rng.roll_dice(1, 4) {
+                    1 if x > 2 => x -= 1,
+                    2 if x < build_data.map.width - 2 => x += 1,
+                    3 if y > 2 => y -= 1,
+                    4 if y < build_data.map.height - 2 => y += 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
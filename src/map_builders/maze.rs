@@ -0,0 +1,59 @@
+use crate::TileType;
+
+use super::{BuilderMap, InitialMapBuilder};
+use rltk::RandomNumberGenerator;
+
+pub struct MazeBuilder {}
+
+impl InitialMapBuilder for MazeBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        self.build(rng, build_data);
+    }
+}
+
+impl MazeBuilder {
+    pub fn new() -> MazeBuilder {
+        MazeBuilder {}
+    }
+
+    /// A perfect maze from a recursive backtracker. Cells sit on odd coordinates, with the
+    /// tile between two neighbouring cells opened up when the walk passes through it.
+    fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let cols = (build_data.map.width - 1) / 2;
+        let rows = (build_data.map.height - 1) / 2;
+        let cell_idx = |cx: i32, cy: i32| (cy * cols + cx) as usize;
+        let cell_xy = |cx: i32, cy: i32| (cx * 2 + 1, cy * 2 + 1);
+
+        let mut visited = vec![false; (cols * rows) as usize];
+        let mut stack = vec![(0, 0)];
+        visited[cell_idx(0, 0)] = true;
+        let (x, y) = cell_xy(0, 0);
+        let idx = build_data.map.xy_idx(x, y);
+        build_data.map.tiles[idx] = TileType::Floor;
+
+        while let Some(&(cx, cy)) = stack.last() {
+            let neighbours: Vec<(i32, i32)> = [(0, -1), (0, 1), (-1, 0), (1, 0)]
+                .iter()
+                .map(|(dx, dy)| (cx + dx, cy + dy))
+                .filter(|(nx, ny)| {
+                    *nx >= 0 && *ny >= 0 && *nx < cols && *ny < rows && !visited[cell_idx(*nx, *ny)]
+                })
+                .collect();
+
+            if neighbours.is_empty() {
+                stack.pop();
+                continue;
+            }
+
+            let (nx, ny) = neighbours[(rng.roll_dice(1, neighbours.len() as i32) - 1) as usize];
+            visited[cell_idx(nx, ny)] = true;
+            let (x, y) = cell_xy(cx, cy);
+            let (next_x, next_y) = cell_xy(nx, ny);
+            for (tx, ty) in [((x + next_x) / 2, (y + next_y) / 2), (next_x, next_y)] {
+                let idx = build_data.map.xy_idx(tx, ty);
+                build_data.map.tiles[idx] = TileType::Floor;
+            }
+            stack.push((nx, ny));
+        }
+    }
+}
@@ -1,19 +1,274 @@
+use crate::rect::Rect;
+use crate::spawner;
 use crate::Position;
 
 use super::Map;
+mod area_based;
+mod bsp_dungeon;
+mod cellular_automata;
+mod corridors;
+mod drunkard;
+mod maze;
+mod room_based;
+mod room_exploder;
+mod rounding;
 mod simple_map;
+mod symmetry;
+use area_based::{AreaStartingPosition, CullUnreachable, DistantExit, VoronoiSpawning};
+use bsp_dungeon::BspDungeonBuilder;
+use cellular_automata::CellularAutomataBuilder;
+use corridors::{DoglegCorridors, NearestCorridors};
+use drunkard::DrunkardsWalkBuilder;
+use maze::MazeBuilder;
+use rltk::RandomNumberGenerator;
+use room_based::{RoomBasedSpawner, RoomBasedStairs, RoomBasedStartingPosition};
+use room_exploder::RoomExploder;
+use rounding::CorridorRounder;
 use simple_map::SimpleMapBuilder;
 use specs::World;
+use symmetry::{Symmetry, SymmetryBuilder};
 pub mod common;
 
-pub trait MapBuilder {
-    fn build_map(&mut self);
-    fn spawn_entities(&mut self, ecs: &mut World);
-    fn get_map(&self) -> Map;
-    fn get_starting_position(&self) -> Position;
+/// Everything a chain of builders works on. Each step reads what the previous ones left and
+/// adds its own part.
+pub struct BuilderMap {
+    pub map: Map,
+    pub starting_position: Option<Position>,
+    pub rooms: Option<Vec<Rect>>,
+    // Groups of tiles that each get their own roll on the spawn table
+    pub spawn_areas: Vec<Vec<usize>>,
 }
 
-pub fn random_builder(depth: i32, width: i32, height: i32) -> Box<dyn MapBuilder> {
-    // Note that until we have a second map type, this isn't even slighlty random
-    Box::new(SimpleMapBuilder::new(depth, width, height))
+/// Lays down the first version of a map
+pub trait InitialMapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap);
+}
+
+/// Modifies a map that an earlier builder has laid down
+pub trait MetaMapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap);
+}
+
+pub struct BuilderChain {
+    starter: Option<Box<dyn InitialMapBuilder>>,
+    builders: Vec<Box<dyn MetaMapBuilder>>,
+    pub build_data: BuilderMap,
+}
+
+impl BuilderChain {
+    pub fn new(depth: i32, width: i32, height: i32) -> BuilderChain {
+        BuilderChain {
+            starter: None,
+            builders: Vec::new(),
+            build_data: BuilderMap {
+                map: Map::new(depth, width, height),
+                starting_position: None,
+                rooms: None,
+                spawn_areas: Vec::new(),
+            },
+        }
+    }
+
+    pub fn start_with(&mut self, starter: Box<dyn InitialMapBuilder>) -> &mut Self {
+        match self.starter {
+            None => self.starter = Some(starter),
+            Some(_) => panic!("You can only have one starting builder."),
+        };
+        self
+    }
+
+    pub fn with(&mut self, metabuilder: Box<dyn MetaMapBuilder>) -> &mut Self {
+        self.builders.push(metabuilder);
+        self
+    }
+
+    pub fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        match &mut self.starter {
+            None => panic!("Cannot run a map builder chain without a starting build system"),
+            Some(starter) => starter.build_map(rng, &mut self.build_data),
+        }
+        for metabuilder in self.builders.iter_mut() {
+            metabuilder.build_map(rng, &mut self.build_data);
+        }
+    }
+
+    pub fn spawn_entities(&self, ecs: &mut World) {
+        for area in self.build_data.spawn_areas.iter() {
+            spawner::spawn_region(ecs, area, self.build_data.map.depth);
+        }
+    }
+
+    pub fn get_map(&self) -> Map {
+        self.build_data.map.clone()
+    }
+
+    pub fn get_starting_position(&self) -> Position {
+        self.build_data
+            .starting_position
+            .clone()
+            .expect("The builder chain never picked a starting position")
+    }
+}
+
+// Room layouts know where their rooms are, so start, stairs and spawns go by room
+fn room_builder(rng: &mut RandomNumberGenerator, chain: &mut BuilderChain) {
+    match rng.roll_dice(1, 2) {
+        1 => chain
+            .start_with(Box::new(SimpleMapBuilder::new()))
+            .with(Box::new(NearestCorridors::new())),
+        _ => chain
+            .start_with(Box::new(BspDungeonBuilder::new()))
+            .with(Box::new(DoglegCorridors::new())),
+    };
+    if rng.roll_dice(1, 3) == 1 {
+        chain.with(Box::new(RoomExploder::new()));
+    }
+    if rng.roll_dice(1, 2) == 1 {
+        chain.with(Box::new(CorridorRounder::new()));
+    }
+    chain
+        .with(Box::new(RoomBasedStartingPosition::new()))
+        .with(Box::new(RoomBasedStairs::new()))
+        .with(Box::new(RoomBasedSpawner::new()));
+}
+
+// Everything else starts near the middle, walls off what it can't reach and puts the stairs
+// as far away as possible
+fn area_builder(rng: &mut RandomNumberGenerator, chain: &mut BuilderChain) {
+    match rng.roll_dice(1, 3) {
+        1 => chain.start_with(Box::new(CellularAutomataBuilder::new())),
+        2 => chain.start_with(Box::new(DrunkardsWalkBuilder::new())),
+        _ => chain.start_with(Box::new(MazeBuilder::new())),
+    };
+    if rng.roll_dice(1, 4) == 1 {
+        let mode = match rng.roll_dice(1, 3) {
+            1 => Symmetry::Horizontal,
+            2 => Symmetry::Vertical,
+            _ => Symmetry::Both,
+        };
+        chain.with(Box::new(SymmetryBuilder::new(mode)));
+    }
+    chain
+        .with(Box::new(AreaStartingPosition::new()))
+        .with(Box::new(CullUnreachable::new()))
+        .with(Box::new(DistantExit::new()))
+        .with(Box::new(VoronoiSpawning::new()));
+}
+
+pub fn random_builder(
+    depth: i32,
+    width: i32,
+    height: i32,
+    rng: &mut RandomNumberGenerator,
+) -> BuilderChain {
+    let mut chain = BuilderChain::new(depth, width, height);
+    // The first floor is always plain rooms, so a new game starts somewhere familiar
+    if depth == 1 {
+        chain
+            .start_with(Box::new(SimpleMapBuilder::new()))
+            .with(Box::new(NearestCorridors::new()))
+            .with(Box::new(RoomBasedStartingPosition::new()))
+            .with(Box::new(RoomBasedStairs::new()))
+            .with(Box::new(RoomBasedSpawner::new()));
+    } else if rng.roll_dice(1, 2) == 1 {
+        room_builder(rng, &mut chain);
+    } else {
+        area_builder(rng, &mut chain);
+    }
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::TileType;
+
+    // Fixed seeds, so a failure here fails every run and can be replayed
+    const SEEDS: [u64; 4] = [1, 7, 42, 1234];
+
+    fn assert_stairs_reachable(chain: &mut BuilderChain, seed: u64) {
+        chain.build_map(&mut RandomNumberGenerator::seeded(seed));
+        let mut map = chain.get_map();
+        let start = chain.get_starting_position();
+        let start_idx = map.xy_idx(start.x, start.y);
+        assert_eq!(map.tiles[start_idx], TileType::Floor);
+
+        let stairs_idx = map
+            .tiles
+            .iter()
+            .position(|tile| *tile == TileType::DownStairs)
+            .expect("No stairs were placed");
+        map.populate_blocked();
+        let dijkstra_map = rltk::DijkstraMap::new(
+            map.width as usize,
+            map.height as usize,
+            &[start_idx],
+            &map,
+            10000.0,
+        );
+        assert!(dijkstra_map.map[stairs_idx] < f32::MAX, "seed {}", seed);
+    }
+
+    #[test]
+    fn test_builders_reach_stairs() {
+        let room_starters: Vec<fn() -> Box<dyn InitialMapBuilder>> = vec![
+            || Box::new(SimpleMapBuilder::new()),
+            || Box::new(BspDungeonBuilder::new()),
+        ];
+        for starter in &room_starters {
+            for seed in SEEDS {
+                let mut chain = BuilderChain::new(1, 80, 50);
+                chain
+                    .start_with(starter())
+                    .with(Box::new(DoglegCorridors::new()))
+                    .with(Box::new(RoomExploder::new()))
+                    .with(Box::new(CorridorRounder::new()))
+                    .with(Box::new(RoomBasedStartingPosition::new()))
+                    .with(Box::new(RoomBasedStairs::new()))
+                    .with(Box::new(RoomBasedSpawner::new()));
+                assert_stairs_reachable(&mut chain, seed);
+            }
+        }
+
+        let area_starters: Vec<fn() -> Box<dyn InitialMapBuilder>> = vec![
+            || Box::new(CellularAutomataBuilder::new()),
+            || Box::new(DrunkardsWalkBuilder::new()),
+            || Box::new(MazeBuilder::new()),
+        ];
+        for starter in &area_starters {
+            for seed in SEEDS {
+                let mut chain = BuilderChain::new(1, 80, 50);
+                chain
+                    .start_with(starter())
+                    .with(Box::new(SymmetryBuilder::new(Symmetry::Both)))
+                    .with(Box::new(AreaStartingPosition::new()))
+                    .with(Box::new(CullUnreachable::new()))
+                    .with(Box::new(DistantExit::new()))
+                    .with(Box::new(VoronoiSpawning::new()));
+                assert_stairs_reachable(&mut chain, seed);
+            }
+        }
+
+        for seed in SEEDS {
+            let mut rng = RandomNumberGenerator::seeded(seed);
+            for depth in 1..10 {
+                assert_stairs_reachable(&mut random_builder(depth, 80, 50, &mut rng), seed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_seeded_levels_repeat() {
+        for seed in SEEDS.map(crate::RunSeed::new) {
+            for depth in 1..6 {
+                let build = || {
+                    let mut rng = seed.level_rng(depth);
+                    let mut chain = random_builder(depth, 80, 50, &mut rng);
+                    chain.build_map(&mut rng);
+                    (chain.get_map().tiles, chain.build_data.spawn_areas)
+                };
+                assert!(build() == build());
+            }
+        }
+    }
 }
@@ -0,0 +1,67 @@
+use crate::rect::Rect;
+use crate::{Position, TileType};
+
+use super::common::*;
+use super::{BuilderMap, MetaMapBuilder};
+use rltk::RandomNumberGenerator;
+
+fn rooms_of(build_data: &BuilderMap) -> &[Rect] {
+    match &build_data.rooms {
+        Some(rooms) => rooms,
+        None => panic!("Room based placement only works after rooms have been created"),
+    }
+}
+
+/// Starts the player in the middle of the first room
+pub struct RoomBasedStartingPosition {}
+
+impl MetaMapBuilder for RoomBasedStartingPosition {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let (x, y) = rooms_of(build_data)[0].center();
+        build_data.starting_position = Some(Position { x, y });
+    }
+}
+
+impl RoomBasedStartingPosition {
+    pub fn new() -> RoomBasedStartingPosition {
+        RoomBasedStartingPosition {}
+    }
+}
+
+/// Puts the way down in the middle of the last room
+pub struct RoomBasedStairs {}
+
+impl MetaMapBuilder for RoomBasedStairs {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let rooms = rooms_of(build_data);
+        let (x, y) = rooms[rooms.len() - 1].center();
+        let stairs_idx = build_data.map.xy_idx(x, y);
+        build_data.map.tiles[stairs_idx] = TileType::DownStairs;
+    }
+}
+
+impl RoomBasedStairs {
+    pub fn new() -> RoomBasedStairs {
+        RoomBasedStairs {}
+    }
+}
+
+/// Every room except the first one gets its own spawns
+pub struct RoomBasedSpawner {}
+
+impl MetaMapBuilder for RoomBasedSpawner {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let areas: Vec<Vec<usize>> = rooms_of(build_data)
+            .iter()
+            .skip(1)
+            .map(|room| room_tiles(&build_data.map, room))
+            .collect();
+        build_data.spawn_areas.extend(areas);
+    }
+}
+
+impl RoomBasedSpawner {
+    pub fn new() -> RoomBasedSpawner {
+        RoomBasedSpawner {}
+    }
+}
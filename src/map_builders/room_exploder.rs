@@ -0,0 +1,44 @@
+use crate::TileType;
+
+use super::{BuilderMap, MetaMapBuilder};
+use rltk::RandomNumberGenerator;
+
+/// Sends a few short-lived diggers out of every room, roughing the walls up into caves
+pub struct RoomExploder {}
+
+impl MetaMapBuilder for RoomExploder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        const DIGGER_LIFETIME: i32 = 20;
+
+        let rooms = match &build_data.rooms {
+            Some(rooms) => rooms.clone(),
+            None => panic!("Room explosions require a builder with room structures"),
+        };
+        let map = &mut build_data.map;
+
+        for room in rooms.iter() {
+            let (start_x, start_y) = room.center();
+            let n_diggers = rng.roll_dice(1, 20) - 5;
+            for _ in 0..n_diggers {
+                let (mut x, mut y) = (start_x, start_y);
+                for _ in 0..DIGGER_LIFETIME {
+                    let idx = map.xy_idx(x, y);
+                    map.tiles[idx] = TileType::Floor;
+                    match rng.roll_dice(1, 4) {
+                        1 if x > 2 => x -= 1,
+                        2 if x < map.width - 2 => x += 1,
+                        3 if y > 2 => y -= 1,
+                        4 if y < map.height - 2 => y += 1,
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl RoomExploder {
+    pub fn new() -> RoomExploder {
+        RoomExploder {}
+    }
+}
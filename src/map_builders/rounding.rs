@@ -0,0 +1,44 @@
+use crate::TileType;
+
+use super::{BuilderMap, MetaMapBuilder};
+use rltk::RandomNumberGenerator;
+
+/// Cuts the inside corner off every right-angle turn, so corridors bend rather than snap
+pub struct CorridorRounder {}
+
+impl MetaMapBuilder for CorridorRounder {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let map = &mut build_data.map;
+        let is_floor = |tiles: &[TileType], idx: usize| tiles[idx] == TileType::Floor;
+
+        let mut new_tiles = map.tiles.clone();
+        for y in 1..map.height - 1 {
+            for x in 1..map.width - 1 {
+                let idx = map.xy_idx(x, y);
+                if map.tiles[idx] != TileType::Wall {
+                    continue;
+                }
+                // A wall with floor on two perpendicular sides, and across the corner between
+                // them, is the inside of a turn
+                for (dx, dy) in [(-1, -1), (1, -1), (-1, 1), (1, 1)] {
+                    let horizontal = map.xy_idx(x + dx, y);
+                    let vertical = map.xy_idx(x, y + dy);
+                    let diagonal = map.xy_idx(x + dx, y + dy);
+                    if is_floor(&map.tiles, horizontal)
+                        && is_floor(&map.tiles, vertical)
+                        && is_floor(&map.tiles, diagonal)
+                    {
+                        new_tiles[idx] = TileType::Floor;
+                    }
+                }
+            }
+        }
+        map.tiles = new_tiles;
+    }
+}
+
+impl CorridorRounder {
+    pub fn new() -> CorridorRounder {
+        CorridorRounder {}
+    }
+}
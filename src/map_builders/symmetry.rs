@@ -0,0 +1,47 @@
+use super::{BuilderMap, MetaMapBuilder};
+use rltk::RandomNumberGenerator;
+
+pub enum Symmetry {
+    Horizontal,
+    Vertical,
+    Both,
+}
+
+/// Mirrors one half of the map onto the other. Halves that don't meet in the middle are left
+/// for `CullUnreachable` to clean up.
+pub struct SymmetryBuilder {
+    mode: Symmetry,
+}
+
+impl MetaMapBuilder for SymmetryBuilder {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let map = &mut build_data.map;
+        let mirror_x = matches!(self.mode, Symmetry::Horizontal | Symmetry::Both);
+        let mirror_y = matches!(self.mode, Symmetry::Vertical | Symmetry::Both);
+
+        if mirror_x {
+            for y in 0..map.height {
+                for x in 0..map.width / 2 {
+                    let src = map.xy_idx(x, y);
+                    let dest = map.xy_idx(map.width - 1 - x, y);
+                    map.tiles[dest] = map.tiles[src];
+                }
+            }
+        }
+        if mirror_y {
+            for y in 0..map.height / 2 {
+                for x in 0..map.width {
+                    let src = map.xy_idx(x, y);
+                    let dest = map.xy_idx(x, map.height - 1 - y);
+                    map.tiles[dest] = map.tiles[src];
+                }
+            }
+        }
+    }
+}
+
+impl SymmetryBuilder {
+    pub fn new(mode: Symmetry) -> SymmetryBuilder {
+        SymmetryBuilder { mode }
+    }
+}
@@ -1,37 +1,171 @@
 use std::collections::HashSet;
 
-use crate::{config::INPUT, gui, systems::item::use_item};
+use crate::raws::{faction_reaction, get_schools, spawn_named_spell, Reaction, RAWS};
+use crate::systems::item::{throw_item, use_item};
+use crate::{camera, config::INPUT, gui, systems::spell::cast_spell};
 
 use super::gamelog::GameLog;
 use itertools::Itertools;
-use rltk::{Point, Rltk, VirtualKeyCode};
+use serde::{Deserialize, Serialize};
+use rltk::{Point, RandomNumberGenerator, Rltk, VirtualKeyCode};
 use specs::prelude::*;
 
-use super::{components, config, map, systems, RunState, State};
+use super::{components, config, map, PlayerInput, RunState, State, TileType};
 pub use components::*;
 
-pub fn make_character(ecs: &mut World) {
-    // Here goes a function that initializes all of the rpgish character stuff
-    // for now we just add a spell to the hotbar
+pub fn make_character(ecs: &mut World, schools: &[usize]) {
+    // Each chosen school grants its starting spells and tweaks the player's stats
+    let raws = RAWS.lock().unwrap();
+    let all_schools = get_schools(&raws);
+    let hotkeys = config::CONFIG.hotkeys();
+    let player_entity = *ecs.fetch::<Entity>();
 
-    // TODO: look at the selection from prev menu and make diff spell
-    systems::spell::fireball_spell(ecs, config::CONFIG.hk1.clone());
+    let mut school_names = Vec::new();
+    let mut spell_names = Vec::new();
+    for school in schools.iter().map(|idx| &all_schools[*idx]) {
+        school_names.push(school.name.clone());
+        spell_names.extend(school.starting_spells.iter().cloned());
+
+        if let Some(modifiers) = &school.stat_modifiers {
+            let mut entity_stats = ecs.write_storage::<EntityStats>();
+            if let Some(stats) = entity_stats.get_mut(player_entity) {
+                for (stat, amount) in modifiers.iter() {
+                    match stat.as_str() {
+                        "power" => stats.power += amount,
+                        "defense" => stats.defense += amount,
+                        _ if stats.pools.contains_key(stat) => {
+                            let (current, max) = stats.get(stat);
+                            stats.set_max(stat, max + amount);
+                            stats.set_current(stat, current + amount);
+                        }
+                        _ => rltk::console::log(format!(
+                            "Warning: school stat modifier {} not implemented.",
+                            stat
+                        )),
+                    }
+                }
+            }
+        }
+    }
+
+    for (spell, hotkey) in spell_names.iter().zip(hotkeys) {
+        spawn_named_spell(&raws, ecs.create_entity(), spell, hotkey);
+    }
+
+    ecs.write_storage::<MagicSchools>()
+        .insert(player_entity, MagicSchools { names: school_names })
+        .expect("Unable to insert schools");
+}
+
+pub enum LevelUpChoice {
+    LearnSpell { name: String },
+    RaisePool { pool: String, amount: i32 },
+}
+
+impl LevelUpChoice {
+    pub fn describe(&self) -> String {
+        match self {
+            LevelUpChoice::LearnSpell { name } => format!("Learn {}", name),
+            LevelUpChoice::RaisePool { pool, amount } => format!("+{} max {}", amount, pool),
+        }
+    }
+}
+
+pub fn has_pending_level_up(ecs: &World) -> bool {
+    let player_entity = ecs.fetch::<Entity>();
+    ecs.read_storage::<PendingLevelUp>()
+        .get(*player_entity)
+        .is_some()
+}
+
+/// Unlearned spells from the player's schools, followed by pool increases
+pub fn level_up_choices(ecs: &World) -> Vec<LevelUpChoice> {
+    let mut choices = Vec::new();
+    let player_entity = ecs.fetch::<Entity>();
+
+    let spells = ecs.read_storage::<Spell>();
+    let names = ecs.read_storage::<Name>();
+    let known: HashSet<&String> = (&spells, &names).join().map(|(_, name)| &name.name).collect();
+
+    if !free_hotkeys(ecs).is_empty() {
+        if let Some(schools) = ecs.read_storage::<MagicSchools>().get(*player_entity) {
+            let raws = RAWS.lock().unwrap();
+            for school in get_schools(&raws)
+                .iter()
+                .filter(|school| schools.names.contains(&school.name))
+            {
+                for spell in school.starting_spells.iter().chain(school.spells.iter()) {
+                    if !known.contains(spell) {
+                        choices.push(LevelUpChoice::LearnSpell {
+                            name: spell.clone(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    choices.push(LevelUpChoice::RaisePool {
+        pool: "hit_points".to_string(),
+        amount: 5,
+    });
+    choices.push(LevelUpChoice::RaisePool {
+        pool: "mana".to_string(),
+        amount: 3,
+    });
+    choices
+}
+
+pub fn apply_level_up(ecs: &mut World, choice: &LevelUpChoice) {
+    let player_entity = *ecs.fetch::<Entity>();
+    match choice {
+        LevelUpChoice::LearnSpell { name } => {
+            if let Some(hotkey) = free_hotkeys(ecs).into_iter().next() {
+                spawn_named_spell(&RAWS.lock().unwrap(), ecs.create_entity(), name, hotkey);
+            }
+        }
+        LevelUpChoice::RaisePool { pool, amount } => {
+            let mut entity_stats = ecs.write_storage::<EntityStats>();
+            if let Some(stats) = entity_stats.get_mut(player_entity) {
+                let (_, max) = stats.get(pool);
+                stats.set_max(pool, max + amount);
+                stats.restore(pool, *amount);
+            }
+        }
+    }
+
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    gamelog.entries.push(format!("Level up! {}", choice.describe()));
+
+    let mut pending = ecs.write_storage::<PendingLevelUp>();
+    let mut done = false;
+    if let Some(pending) = pending.get_mut(player_entity) {
+        pending.levels -= 1;
+        done = pending.levels < 1;
+    }
+    if done {
+        pending.remove(player_entity);
+    }
 }
 
 fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState {
-    let mut positions = ecs.write_storage::<Position>();
-    let mut players = ecs.write_storage::<Player>();
-    let mut viewsheds = ecs.write_storage::<Viewshed>();
+    let (delta_x, delta_y) = confused_direction(ecs, delta_x, delta_y);
+    let positions = ecs.read_storage::<Position>();
+    let players = ecs.read_storage::<Player>();
 
-    let antagonists = ecs.read_storage::<Antagonistic>();
+    let factions = ecs.read_storage::<Faction>();
+    let status_effects = ecs.read_storage::<StatusEffects>();
+    let quips = ecs.read_storage::<Quips>();
+    let names = ecs.read_storage::<Name>();
     let map = ecs.fetch::<map::Map>();
 
     let entities = ecs.entities();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
+    let mut moves = ecs.write_storage::<ApplyMove>();
+    let mut teleports = ecs.write_storage::<ApplyTeleport>();
+    let raws = RAWS.lock().unwrap();
 
-    for (entity, _player, pos, viewshed) in
-        (&entities, &mut players, &mut positions, &mut viewsheds).join()
-    {
+    for (entity, _player, pos) in (&entities, &players, &positions).join() {
         if pos.x + delta_x < 1
             || pos.x + delta_x > map.width - 1
             || pos.y + delta_y < 1
@@ -41,9 +175,13 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState {
         }
 
         let destination_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
+        let my_faction = factions.get(entity).map_or("Player", |f| f.name.as_str());
         for potential_target in map.tile_content[destination_idx].iter() {
-            let target = antagonists.get(*potential_target);
-            if let Some(_target) = target {
+            let their_faction = match factions.get(*potential_target) {
+                Some(faction) => faction.allegiance(status_effects.get(*potential_target)),
+                None => continue,
+            };
+            if faction_reaction(my_faction, their_faction, &raws) == Reaction::Attack {
                 wants_to_melee
                     .insert(
                         entity,
@@ -54,15 +192,45 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState {
                     .expect("Add target failed");
                 return RunState::PlayerTurn;
             }
+
+            // Anyone the player isn't out to fight either has something to say, or makes way
+            if let Some(quips) = quips.get(*potential_target) {
+                let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+                if let (Some(line), Some(name)) = (
+                    rng.random_slice_entry(&quips.lines),
+                    names.get(*potential_target),
+                ) {
+                    let mut gamelog = ecs.write_resource::<GameLog>();
+                    gamelog
+                        .entries
+                        .push(format!("{} says, \"{}\"", name.name, line));
+                }
+                return RunState::PlayerTurn;
+            }
+            let (dest_x, dest_y) = (pos.x + delta_x, pos.y + delta_y);
+            teleports
+                .insert(entity, ApplyTeleport { dest_x, dest_y })
+                .expect("Unable to insert teleport");
+            teleports
+                .insert(
+                    *potential_target,
+                    ApplyTeleport {
+                        dest_x: pos.x,
+                        dest_y: pos.y,
+                    },
+                )
+                .expect("Unable to insert teleport");
+            return RunState::PlayerTurn;
         }
         if !map.blocked[destination_idx] {
-            pos.x = i32::clamp(pos.x + delta_x, 0, map.width - 1);
-            pos.y = i32::clamp(pos.y + delta_y, 0, map.height - 1);
-
-            let mut ppos = ecs.write_resource::<Point>();
-            ppos.x = pos.x;
-            ppos.y = pos.y;
-            viewshed.dirty = true;
+            moves
+                .insert(
+                    entity,
+                    ApplyMove {
+                        dest_idx: destination_idx,
+                    },
+                )
+                .expect("Unable to insert move");
         } else {
             return RunState::AwaitingInput;
         }
@@ -70,32 +238,35 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState {
     RunState::PlayerTurn
 }
 
-fn _cast_spell(ecs: &mut World) {
+// A confused player stumbles in a random direction instead
+fn confused_direction(ecs: &mut World, delta_x: i32, delta_y: i32) -> (i32, i32) {
     let player_entity = ecs.fetch::<Entity>();
-    let cursor = ecs.fetch::<Cursor>();
-    let mut castables = ecs.write_storage::<WantsToCastSpell>();
-    // having some trouble with ecs here. spell isn't passed in here, we join it based on
-    // keypress???
-
-    // return RunState::ShowTargeting { range: (), item: () },
-
-    //gui::ranged_target(ecs, ctx: &mut Rltk, range: i32);
-    // -> SelectResult
-
-    // Flow: press C, 1
-    // move cursor, hit enter
-    // spell takes effect
-    //
-    // Do we have a
-    castables
-        .insert(
-            *player_entity,
-            WantsToCastSpell {
-                source: *player_entity,
-                target: Some(cursor.point),
-            },
-        )
-        .expect("Unable to insert want to cast");
+    let status_effects = ecs.read_storage::<StatusEffects>();
+    if let Some(effects) = status_effects.get(*player_entity) {
+        if effects.has(StatusType::Confusion) {
+            let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+            return match rng.roll_dice(1, 4) {
+                1 => (-1, 0),
+                2 => (1, 0),
+                3 => (0, -1),
+                _ => (0, 1),
+            };
+        }
+    }
+    (delta_x, delta_y)
+}
+
+pub fn player_skips_turn(ecs: &mut World) -> bool {
+    let player_entity = ecs.fetch::<Entity>();
+    let status_effects = ecs.read_storage::<StatusEffects>();
+    if let Some(effects) = status_effects.get(*player_entity) {
+        if effects.skips_turn() {
+            let mut gamelog = ecs.fetch_mut::<GameLog>();
+            gamelog.entries.push("You are unable to act.".to_string());
+            return true;
+        }
+    }
+    false
 }
 
 fn get_item(ecs: &mut World) -> RunState {
@@ -133,72 +304,290 @@ fn get_item(ecs: &mut World) -> RunState {
     RunState::PlayerTurn
 }
 
-fn use_hotkey(ecs: &mut World, key: VirtualKeyCode) -> RunState {
-    let hotkeys = vec![INPUT.hk1, INPUT.hk2, INPUT.hk3, INPUT.hk4, INPUT.hk5, INPUT.hk6, INPUT.hk7, INPUT.hk8, INPUT.hk9, INPUT.hk10];
+/// Hotkey labels left over for the inventory once spells have claimed theirs
+pub fn free_hotkeys(ecs: &World) -> Vec<String> {
+    let spells = ecs.read_storage::<Spell>();
+    let bound: HashSet<&String> = spells.join().map(|spell| &spell.hotkey).collect();
+    config::CONFIG
+        .hotkeys()
+        .into_iter()
+        .filter(|hotkey| !bound.contains(hotkey))
+        .collect()
+}
 
-    let index = hotkeys.iter().position(|obj| *obj == key).unwrap();
+fn use_hotkey(ecs: &mut World, index: usize) -> RunState {
+    let hotkey = match config::CONFIG.hotkeys().get(index) {
+        Some(hotkey) => hotkey.clone(),
+        None => return RunState::AwaitingInput,
+    };
 
-    let mut carried_consumables = Vec::new();
+    // Spells bound to a key take precedence over the inventory
+    let mut bound_spell: Option<Entity> = None;
     {
-        let mut seen = HashSet::<String>::new();
-        let backpack = ecs.read_storage::<InBackpack>();
-        let names = ecs.read_storage::<Name>();
-        let player_entity = ecs.fetch::<Entity>();
+        let spells = ecs.read_storage::<Spell>();
         let entities = ecs.entities();
-        for (entity, _carried_by, name) in (&entities, &backpack, &names)
-            .join()
-            .filter(|item| item.1.owner == *player_entity)
-            .sorted_by(|a, b| Ord::cmp(&a.2.name, &b.2.name))
-        {
-            if !seen.contains(&name.name) {
-                carried_consumables.push(entity);
-                seen.insert(name.name.clone());
+        for (entity, spell) in (&entities, &spells).join() {
+            if spell.hotkey == hotkey {
+                bound_spell = Some(entity);
             }
         }
     }
+    if let Some(spell) = bound_spell {
+        return cast_spell(ecs, spell);
+    }
+
+    let index = match free_hotkeys(ecs).iter().position(|free| *free == hotkey) {
+        Some(index) => index,
+        None => return RunState::AwaitingInput,
+    };
+
+    match inventory_stacks(ecs).get(index) {
+        Some(stack) => use_item(ecs, stack.item),
+        None => RunState::PlayerTurn,
+    }
+}
 
-    if index < carried_consumables.len() {
-        let item = carried_consumables.get(index);
-        match item {
-            Some(item) => return use_item(ecs, *item),
-            None => return RunState::AwaitingInput,
+/// Everything of one name the player carries
+pub struct ItemStack {
+    pub item: Entity,
+    pub name: String,
+    pub count: usize,
+}
+
+/// The player's inventory, one stack per name, in name order. Hotkeys and inventory actions
+/// both count through this list.
+pub fn inventory_stacks(ecs: &World) -> Vec<ItemStack> {
+    let backpack = ecs.read_storage::<InBackpack>();
+    let names = ecs.read_storage::<Name>();
+    let player_entity = ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+
+    let mut stacks: Vec<ItemStack> = Vec::new();
+    for (entity, _carried_by, name) in (&entities, &backpack, &names)
+        .join()
+        .filter(|item| item.1.owner == *player_entity)
+        .sorted_by(|a, b| Ord::cmp(&a.2.name, &b.2.name))
+    {
+        match stacks.last_mut() {
+            Some(stack) if stack.name == name.name => stack.count += 1,
+            _ => stacks.push(ItemStack {
+                item: entity,
+                name: name.name.clone(),
+                count: 1,
+            }),
         }
     }
-    RunState::PlayerTurn
+    stacks
 }
 
 // TODO: walking into a corpse doesn't work. maybe we aren't marking the right thing as dirty?
 
-// TODO: protect from overflow on char/item select window
-pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
-    let hotkeys = vec![INPUT.hk1, INPUT.hk2, INPUT.hk3, INPUT.hk4, INPUT.hk5, INPUT.hk6, INPUT.hk7, INPUT.hk8, INPUT.hk9, INPUT.hk10];
+/// Everything the player can do on their turn, whether it came from the keyboard or a script
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum PlayerAction {
+    Move { dx: i32, dy: i32 },
+    PickUp,
+    Hotkey { index: usize },
+    Descend,
+    Ascend,
+    Wait,
+    Menu,
+    Inventory,
+    Look,
+    UseItem { index: usize },
+    DropItem { index: usize },
+    ThrowItem { index: usize },
+}
 
-    match ctx.key {
-        None => RunState::AwaitingInput, // Nothing happened
-        Some(key) => match key {
-            // TODO: I still don't understand why I have to do do `_ if key ==`
-            _ if key == INPUT.left => try_move_player(-1, 0, &mut gs.ecs),
-            _ if key == INPUT.down => try_move_player(0, 1, &mut gs.ecs),
-            _ if key == INPUT.up => try_move_player(0, -1, &mut gs.ecs),
-            _ if key == INPUT.right => try_move_player(1, 0, &mut gs.ecs),
-
-            _ if key == INPUT.pick_up => get_item(&mut gs.ecs),
-
-            _ if hotkeys.contains(&key) => use_hotkey(&mut gs.ecs, key),
-            // cast_spell(&mut gs.ecs),
-            _ if key == INPUT.select => {
-                // refactor to be context-dependant on tile
-                if map::try_next_level(&mut gs.ecs) {
-                    return RunState::NextLevel;
-                }
-                RunState::AwaitingInput
+impl PlayerAction {
+    /// Whether doing it spends the player's turn, rather than opening up a screen
+    pub fn takes_turn(&self) -> bool {
+        !matches!(
+            self,
+            PlayerAction::Menu | PlayerAction::Inventory | PlayerAction::Look
+        )
+    }
+}
+
+fn key_action(ecs: &World, key: VirtualKeyCode) -> Option<PlayerAction> {
+    let hotkeys = INPUT.hotkeys();
+    match key {
+        // TODO: I still don't understand why I have to do do `_ if key ==`
+        _ if key == INPUT.left => Some(PlayerAction::Move { dx: -1, dy: 0 }),
+        _ if key == INPUT.down => Some(PlayerAction::Move { dx: 0, dy: 1 }),
+        _ if key == INPUT.up => Some(PlayerAction::Move { dx: 0, dy: -1 }),
+        _ if key == INPUT.right => Some(PlayerAction::Move { dx: 1, dy: 0 }),
+
+        _ if key == INPUT.pick_up => Some(PlayerAction::PickUp),
+
+        _ if hotkeys.contains(&key) => Some(PlayerAction::Hotkey {
+            index: hotkeys.iter().position(|obj| *obj == key).unwrap(),
+        }),
+        _ if key == INPUT.select => match map::player_tile(ecs) {
+            TileType::UpStairs => Some(PlayerAction::Ascend),
+            _ => Some(PlayerAction::Descend),
+        },
+        _ if key == INPUT.exit => Some(PlayerAction::Menu),
+        _ if key == INPUT.wait => Some(PlayerAction::Wait),
+        _ if key == INPUT.inventory => Some(PlayerAction::Inventory),
+        _ if key == INPUT.inspect => Some(PlayerAction::Look),
+        _ => None,
+    }
+}
+
+pub fn perform_action(ecs: &mut World, action: PlayerAction) -> RunState {
+    match action {
+        PlayerAction::Move { dx, dy } => try_move_player(dx, dy, ecs),
+        PlayerAction::PickUp => get_item(ecs),
+        PlayerAction::Hotkey { index } => use_hotkey(ecs, index),
+        PlayerAction::Descend => {
+            if map::try_next_level(ecs) {
+                return RunState::NextLevel;
             }
-            _ if key == INPUT.exit => RunState::MainMenu {
-                game_started: true,
-                menu_selection: gui::MainMenuSelection::NewGame,
-            },
-            _ if key == INPUT.wait => RunState::PlayerTurn,
-            _ => RunState::AwaitingInput,
+            RunState::AwaitingInput
+        }
+        PlayerAction::Ascend => {
+            if map::try_previous_level(ecs) {
+                return RunState::PreviousLevel;
+            }
+            RunState::AwaitingInput
+        }
+        PlayerAction::Menu => RunState::MainMenu {
+            game_started: true,
+            menu_selection: gui::MainMenuSelection::NewGame,
+        },
+        PlayerAction::Wait => RunState::PlayerTurn,
+        PlayerAction::Inventory => RunState::ShowInventory {
+            selection: 0,
+            sort: gui::InventorySort::Name,
+            inspecting: false,
+        },
+        PlayerAction::Look => {
+            let player_pos = *ecs.fetch::<Point>();
+            let screen_pt = camera::tile_to_screen(ecs, player_pos);
+            ecs.fetch_mut::<Cursor>().point = screen_pt;
+            RunState::Examine
+        }
+        PlayerAction::UseItem { index } => match inventory_stacks(ecs).get(index) {
+            Some(stack) => use_item(ecs, stack.item),
+            None => RunState::AwaitingInput,
+        },
+        PlayerAction::DropItem { index } => match inventory_stacks(ecs).get(index) {
+            Some(stack) => {
+                let player_entity = *ecs.fetch::<Entity>();
+                ecs.write_storage::<WantsToDropItem>()
+                    .insert(player_entity, WantsToDropItem { item: stack.item })
+                    .expect("Unable to insert intent");
+                RunState::PlayerTurn
+            }
+            None => RunState::AwaitingInput,
+        },
+        PlayerAction::ThrowItem { index } => match inventory_stacks(ecs).get(index) {
+            Some(stack) => throw_item(ecs, stack.item),
+            None => RunState::AwaitingInput,
         },
     }
 }
+
+// TODO: protect from overflow on char/item select window
+pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
+    match ctx.key.and_then(|key| key_action(&gs.ecs, key)) {
+        None => RunState::AwaitingInput, // Nothing happened
+        Some(action) => gs.apply_input(RunState::AwaitingInput, PlayerInput::Act(action)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::Simulation;
+    use crate::{AiState, StatusEffects, StatusType};
+
+    #[test]
+    fn test_stunned_player_loses_their_input() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        // Into the turn order first, so the stun wears off on schedule
+        sim.input(PlayerInput::Act(PlayerAction::Wait));
+        let pos = sim.player_pos();
+        let player = sim.player();
+        let stun = |sim: &mut Simulation| {
+            let mut effects = StatusEffects::default();
+            effects.add(crate::Status {
+                typ: StatusType::Stun,
+                magnitude: 1,
+                duration: 2,
+            });
+            sim.ecs()
+                .write_storage::<StatusEffects>()
+                .insert(player, effects)
+                .unwrap();
+        };
+        let skipped = |sim: &Simulation| {
+            let log = sim.log();
+            log.iter()
+                .filter(|e| *e == "You are unable to act.")
+                .count()
+        };
+        let step = PlayerAction::Move { dx: 1, dy: 0 };
+
+        // Each turn of the stun waits on an input, and swallows it
+        stun(&mut sim);
+        for _ in 0..2 {
+            let runstate = sim.input(PlayerInput::Act(step));
+            assert!(runstate == RunState::AwaitingInput);
+            assert_eq!(sim.player_pos(), pos);
+        }
+        assert_eq!(skipped(&sim), 2);
+        sim.input(PlayerInput::Act(step));
+        assert_eq!(sim.player_pos(), Point::new(pos.x + 1, pos.y));
+
+        // Looking around doesn't take a turn, so there's none to lose
+        stun(&mut sim);
+        let runstate = sim.input(PlayerInput::Act(PlayerAction::Look));
+        assert!(runstate == RunState::Examine);
+        assert_eq!(skipped(&sim), 2);
+    }
+
+    #[test]
+    fn test_bumping_neutrals() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let pos = sim.player_pos();
+        let rat = sim.spawn("Rat", pos.x + 1, pos.y);
+        let before = sim.hit_points(rat);
+
+        sim.input(PlayerInput::Act(PlayerAction::Move { dx: 1, dy: 0 }));
+        assert_eq!(sim.player_pos(), Point::new(pos.x + 1, pos.y));
+        assert_eq!(sim.hit_points(rat), before);
+        assert!(sim.behaviour(rat).state == AiState::Fleeing);
+
+        let here = sim.player_pos();
+        sim.spawn("Hermit", here.x + 1, here.y);
+        sim.input(PlayerInput::Act(PlayerAction::Move { dx: 1, dy: 0 }));
+        assert_eq!(sim.player_pos(), here);
+        assert!(sim
+            .log()
+            .iter()
+            .any(|entry| entry.starts_with("Hermit says")));
+    }
+
+    #[test]
+    fn test_level_up() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let player = sim.player();
+        let pos = sim.player_pos();
+        let goblin = sim.spawn("Goblin", pos.x, pos.y + 1);
+        sim.set_current(goblin, "hit_points", 1);
+        sim.set_current(player, "xp", 19);
+
+        let runstate = sim.input(PlayerInput::Act(PlayerAction::Move { dx: 0, dy: 1 }));
+        assert!(matches!(runstate, RunState::LevelUp { .. }));
+
+        // Raising mana is always the last choice
+        let choice = level_up_choices(&sim.state.ecs).len() - 1;
+        assert!(sim.input(PlayerInput::LevelUp { choice }) == RunState::AwaitingInput);
+        let stats = sim.ecs().read_storage::<EntityStats>();
+        let stats = stats.get(player).unwrap();
+        assert_eq!(stats.level, 2);
+        assert_eq!(stats.get("mana").1, 13);
+    }
+}
@@ -0,0 +1,27 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Deserialize, Debug)]
+pub struct FactionInfo {
+    pub name: String,
+    pub responses: HashMap<String, String>,
+}
+
+// How one faction treats another. Anything a faction doesn't list gets its "Default" response.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Reaction {
+    Ignore,
+    Attack,
+    Flee,
+}
+
+impl Reaction {
+    pub fn from_name(name: &str) -> Option<Reaction> {
+        match name {
+            "ignore" => Some(Reaction::Ignore),
+            "attack" => Some(Reaction::Attack),
+            "flee" => Some(Reaction::Flee),
+            _ => None,
+        }
+    }
+}
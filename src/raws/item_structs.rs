@@ -4,6 +4,7 @@ use std::collections::HashMap;
 #[derive(Deserialize, Debug)]
 pub struct Item {
     pub name: String,
+    pub description: Option<String>,
     pub stats: Option<Stats>,
     pub renderable: Option<Renderable>,
     pub consumable: Option<Consumable>,
@@ -4,10 +4,19 @@ use serde::Deserialize;
 #[derive(Deserialize, Debug)]
 pub struct Mob {
     pub name: String,
+    pub description: Option<String>,
     pub renderable: Option<Renderable>,
     pub blocks_tile: bool,
     pub stats: MobStats,
     pub vision_range: i32,
+    pub experience: Option<i32>,
+    pub speed: Option<i32>,
+    pub behaviour: Option<String>,
+    pub ranged: Option<MobRanged>,
+    pub spells: Option<Vec<String>>,
+    pub inventory: Option<Vec<String>>,
+    pub faction: Option<String>,
+    pub quips: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -16,4 +25,11 @@ pub struct MobStats {
     pub hp: i32,
     pub power: i32,
     pub defense: i32,
+    pub damage: String,
+    pub mana: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MobRanged {
+    pub range: i32,
 }
@@ -5,9 +5,18 @@ use serde::Deserialize;
 mod mob_structs;
 pub use mob_structs::*;
 
+mod spell_structs;
+pub use spell_structs::*;
+
+mod school_structs;
+pub use school_structs::*;
+
 mod spawn_table_structs;
 pub use spawn_table_structs::*;
 
+mod faction_structs;
+pub use faction_structs::*;
+
 mod rawmaster;
 pub use rawmaster::*;
 use std::sync::Mutex;
@@ -16,7 +25,10 @@ use std::sync::Mutex;
 pub struct Raws {
     pub items: Vec<Item>,
     pub mobs: Vec<Mob>,
+    pub spells: Vec<Spell>,
+    pub schools: Vec<School>,
     pub spawn_table: Vec<SpawnTableEntry>,
+    pub factions: Vec<FactionInfo>,
 }
 
 lazy_static! {
@@ -26,6 +38,10 @@ lazy_static! {
 rltk::embedded_resource!(RAW_FILE, "../../raws/spawns.json");
 
 pub fn load_raws() {
+    RAWS.lock().unwrap().load(parse_raws());
+}
+
+pub fn parse_raws() -> Raws {
     rltk::link_resource!(RAW_FILE, "../../raws/spawns.json");
     // Retrieve the raw data as an array of u8 (8-bit unsigned chars)
     let raw_data = rltk::embedding::EMBED
@@ -34,7 +50,5 @@ pub fn load_raws() {
         .unwrap();
     let raw_string =
         std::str::from_utf8(raw_data).expect("Unable to convert to a valid UTF-8 string.");
-    let decoder: Raws = serde_json::from_str(raw_string).expect("Unable to parse JSON");
-
-    RAWS.lock().unwrap().load(decoder);
+    serde_json::from_str(raw_string).expect("Unable to parse JSON")
 }
@@ -1,4 +1,6 @@
-use super::Raws;
+use super::{Raws, Reaction, School};
+use crate::dice::Dice;
+use crate::systems::initiative::ACTION_COST;
 use crate::{components::*, systems::random_table::RandomTable};
 use specs::saveload::MarkedBuilder;
 use specs::{prelude::*, saveload::SimpleMarker};
@@ -8,8 +10,15 @@ pub struct RawMaster {
     raws: Raws,
     item_index: HashMap<String, usize>,
     mob_index: HashMap<String, usize>,
+    spell_index: HashMap<String, usize>,
+    faction_index: HashMap<String, HashMap<String, Reaction>>,
 }
 
+/// What a mob belongs to when its raw doesn't say: it goes after the player and nothing else
+pub const DEFAULT_FACTION: &str = "Monsters";
+/// Whatever fights on the player's side: summons, and anything charmed
+pub const ALLY_FACTION: &str = "Allies";
+
 // lime_green bfff47
 
 impl RawMaster {
@@ -18,10 +27,15 @@ impl RawMaster {
             raws: Raws {
                 items: Vec::new(),
                 mobs: Vec::new(),
+                spells: Vec::new(),
+                schools: Vec::new(),
                 spawn_table: Vec::new(),
+                factions: Vec::new(),
             },
             item_index: HashMap::new(),
             mob_index: HashMap::new(),
+            spell_index: HashMap::new(),
+            faction_index: HashMap::new(),
         }
     }
 
@@ -49,6 +63,65 @@ impl RawMaster {
             self.mob_index.insert(mob.name.clone(), i);
             used_names.insert(mob.name.clone());
         }
+        for (i, spell) in self.raws.spells.iter().enumerate() {
+            if used_names.contains(&spell.name) {
+                rltk::console::log(format!(
+                    "WARNING -  duplicate spell name in raws [{}]",
+                    spell.name
+                ));
+            }
+            self.spell_index.insert(spell.name.clone(), i);
+            used_names.insert(spell.name.clone());
+        }
+
+        for school in self.raws.schools.iter() {
+            for spell in school.starting_spells.iter().chain(school.spells.iter()) {
+                if !self.spell_index.contains_key(spell) {
+                    rltk::console::log(format!(
+                        "WARNING - School {} references unspecified spell {}",
+                        school.name, spell
+                    ));
+                }
+            }
+        }
+
+        for spell in self.raws.spells.iter() {
+            if let Some(summons) = spell.effects.get("summons") {
+                let mob = parse_summons(summons).mob;
+                if !self.mob_index.contains_key(&mob) {
+                    rltk::console::log(format!(
+                        "WARNING - Spell {} summons unspecified mob {}",
+                        spell.name, mob
+                    ));
+                }
+            }
+        }
+
+        self.faction_index = HashMap::new();
+        for faction in self.raws.factions.iter() {
+            let mut reactions = HashMap::new();
+            for (other, response) in faction.responses.iter() {
+                match Reaction::from_name(response) {
+                    Some(reaction) => {
+                        reactions.insert(other.clone(), reaction);
+                    }
+                    None => rltk::console::log(format!(
+                        "WARNING - Faction {} has unknown response {}",
+                        faction.name, response
+                    )),
+                }
+            }
+            self.faction_index.insert(faction.name.clone(), reactions);
+        }
+        for mob in self.raws.mobs.iter() {
+            let faction = mob.faction.as_deref().unwrap_or(DEFAULT_FACTION);
+            if !self.faction_index.contains_key(faction) {
+                rltk::console::log(format!(
+                    "WARNING - Mob {} belongs to unspecified faction {}",
+                    mob.name, faction
+                ));
+            }
+        }
 
         for spawn in self.raws.spawn_table.iter() {
             if !used_names.contains(&spawn.name) {
@@ -63,6 +136,7 @@ impl RawMaster {
 
 pub enum SpawnType {
     AtPosition { x: i32, y: i32 },
+    Carried { by: Entity },
 }
 
 fn spawn_position(pos: SpawnType, new_entity: EntityBuilder) -> EntityBuilder {
@@ -71,7 +145,11 @@ fn spawn_position(pos: SpawnType, new_entity: EntityBuilder) -> EntityBuilder {
     // Spawn in the specified location
     match pos {
         SpawnType::AtPosition { x, y } => {
-            eb = eb.with(Position { x, y });
+            eb = eb.with(Position { x, y }).with(OnLevel {});
+        }
+        // Only monsters start out carrying anything, and their gear stays on the level with them
+        SpawnType::Carried { by } => {
+            eb = eb.with(InBackpack { owner: by }).with(OnLevel {});
         }
     }
 
@@ -107,6 +185,29 @@ fn parse_particle(n: &str) -> SpawnParticleBurst {
     }
 }
 
+fn parse_inflicts_status(n: &str) -> InflictsStatus {
+    let tokens: Vec<_> = n.split(';').collect();
+    InflictsStatus {
+        typ: StatusType::from_name(tokens[0]).expect("Unknown status"),
+        magnitude: tokens[1].parse::<i32>().unwrap(),
+        duration: tokens[2].parse::<i32>().unwrap(),
+    }
+}
+
+fn parse_summons(n: &str) -> Summons {
+    let tokens: Vec<_> = n.split(';').collect();
+    Summons {
+        mob: tokens[0].to_string(),
+        duration: tokens[1].parse::<i32>().unwrap(),
+    }
+}
+
+fn parse_cures_status(n: &str) -> CuresStatus {
+    CuresStatus {
+        typ: StatusType::from_name(n).expect("Unknown status"),
+    }
+}
+
 macro_rules! apply_effects {
     ( $effects:expr, $eb:expr ) => {
         for effect in $effects.iter() {
@@ -124,7 +225,7 @@ macro_rules! apply_effects {
                 }
                 "damage" => {
                     $eb = $eb.with(InflictsDamage {
-                        damage: effect.1.parse::<i32>().unwrap(),
+                        damage: Dice::parse(effect.1).expect("Bad damage dice"),
                     })
                 }
                 "area_of_effect" => {
@@ -146,6 +247,25 @@ macro_rules! apply_effects {
                         mana_amount: effect.1.parse::<i32>().unwrap(),
                     })
                 }
+                "push" => {
+                    $eb = $eb.with(Pushes {
+                        distance: effect.1.parse::<i32>().unwrap(),
+                    })
+                }
+                "pull" => {
+                    $eb = $eb.with(Pulls {
+                        distance: effect.1.parse::<i32>().unwrap(),
+                    })
+                }
+                "knockback" => {
+                    $eb = $eb.with(KnocksBack {
+                        distance: effect.1.parse::<i32>().unwrap(),
+                    })
+                }
+                "swap" => $eb = $eb.with(SwapsPlaces {}),
+                "inflicts_status" => $eb = $eb.with(parse_inflicts_status(&effect.1)),
+                "cures_status" => $eb = $eb.with(parse_cures_status(&effect.1)),
+                "summons" => $eb = $eb.with(parse_summons(&effect.1)),
                 _ => rltk::console::log(format!(
                     "Warning: consumable effect {} not implemented.",
                     effect_name
@@ -173,6 +293,11 @@ pub fn spawn_named_item(
         eb = eb.with(Name {
             name: item_template.name.clone(),
         });
+        if let Some(description) = &item_template.description {
+            eb = eb.with(Description {
+                text: description.clone(),
+            });
+        }
 
         eb = eb.with(crate::components::Item {});
 
@@ -180,6 +305,8 @@ pub fn spawn_named_item(
             eb = eb.with(EntityStats {
                 power: 0,
                 defense: 0,
+                damage: Dice::flat(0),
+                level: 1,
                 pools: HashMap::from([(
                     "hit_points".to_string(),
                     Pool {
@@ -211,6 +338,11 @@ pub fn spawn_named_mob(
 
         let mut eb = new_entity;
 
+        let world = eb.world;
+        let home = match pos {
+            SpawnType::AtPosition { x, y } => rltk::Point::new(x, y),
+            SpawnType::Carried { .. } => return None,
+        };
         // Spawn in the specified location
         eb = spawn_position(pos, eb);
 
@@ -219,37 +351,131 @@ pub fn spawn_named_mob(
             eb = eb.with(get_renderable_component(renderable));
         }
 
+        let mut pools = HashMap::from([(
+            "hit_points".to_string(),
+            Pool {
+                max: mob_template.stats.max_hp,
+                current: mob_template.stats.hp,
+            },
+        )]);
+        if let Some(mana) = mob_template.stats.mana {
+            pools.insert(
+                "mana".to_string(),
+                Pool {
+                    max: mana,
+                    current: mana,
+                },
+            );
+        }
+        let kind = match &mob_template.behaviour {
+            Some(name) => BehaviourKind::from_name(name).expect("Unknown behaviour"),
+            None => BehaviourKind::Chaser,
+        };
         eb = eb
             .with(Name {
                 name: mob_template.name.clone(),
             })
-            .with(crate::components::Antagonistic {})
+            .with(Behaviour {
+                kind,
+                state: AiState::Idle,
+                last_seen: None,
+                home,
+            })
+            .with(Faction {
+                name: mob_template
+                    .faction
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_FACTION.to_string()),
+            })
             .with(Monster {})
             .with(EntityStats {
                 power: mob_template.stats.power,
                 defense: mob_template.stats.defense,
-                pools: HashMap::from([(
-                    "hit_points".to_string(),
-                    Pool {
-                        max: mob_template.stats.max_hp,
-                        current: mob_template.stats.hp,
-                    },
-                )]),
+                damage: Dice::parse(&mob_template.stats.damage).expect("Bad damage dice"),
+                level: 1,
+                pools,
             })
             .with(Viewshed {
                 visible_tiles: Vec::new(),
                 range: mob_template.vision_range,
                 dirty: true,
             });
+        if let Some(description) = &mob_template.description {
+            eb = eb.with(Description {
+                text: description.clone(),
+            });
+        }
+        if let Some(quips) = &mob_template.quips {
+            eb = eb.with(Quips {
+                lines: quips.clone(),
+            });
+        }
+        if let Some(ranged) = &mob_template.ranged {
+            eb = eb.with(RangedAttack {
+                range: ranged.range,
+            });
+        }
         if mob_template.blocks_tile {
             eb = eb.with(BlocksTile {});
         }
+        eb = eb.with(GrantsExperience {
+            amount: mob_template.experience.unwrap_or(mob_template.stats.max_hp),
+        });
+        eb = eb.with(Initiative {
+            energy: 0,
+            speed: mob_template.speed.unwrap_or(ACTION_COST),
+        });
 
-        return Some(eb.marked::<SimpleMarker<SerializeMe>>().build());
+        let mob = eb.marked::<SimpleMarker<SerializeMe>>().build();
+
+        for spell in mob_template.spells.iter().flatten() {
+            spawn_known_spell(raws, world.create_entity_unchecked(), spell, mob);
+        }
+        for item in mob_template.inventory.iter().flatten() {
+            let carried = SpawnType::Carried { by: mob };
+            spawn_named_item(raws, world.create_entity_unchecked(), item, carried);
+        }
+        return Some(mob);
     }
     None
 }
 
+fn spell_builder<'a>(
+    raws: &RawMaster,
+    new_entity: EntityBuilder<'a>,
+    key: &str,
+) -> Option<EntityBuilder<'a>> {
+    let spell_template = &raws.raws.spells[*raws.spell_index.get(key)?];
+    let mut eb = new_entity.with(Name {
+        name: spell_template.name.clone(),
+    });
+    apply_effects!(spell_template.effects, eb);
+    Some(eb)
+}
+
+/// A spell for a monster to cast, which goes wherever its level goes
+fn spawn_known_spell(
+    raws: &RawMaster,
+    new_entity: EntityBuilder,
+    key: &str,
+    owner: Entity,
+) -> Option<Entity> {
+    let eb = spell_builder(raws, new_entity, key)?
+        .with(KnownSpell { owner })
+        .with(OnLevel {});
+    Some(eb.marked::<SimpleMarker<SerializeMe>>().build())
+}
+
+pub fn spawn_named_spell(
+    raws: &RawMaster,
+    new_entity: EntityBuilder,
+    key: &str,
+    hotkey: String,
+) -> Option<Entity> {
+    let eb = spell_builder(raws, new_entity, key)?.with(crate::components::Spell { hotkey });
+    Some(eb.marked::<SimpleMarker<SerializeMe>>().build())
+}
+
 pub fn spawn_named_entity(
     raws: &RawMaster,
     new_entity: EntityBuilder,
@@ -265,6 +491,27 @@ pub fn spawn_named_entity(
     None
 }
 
+/// How a member of one faction treats a member of another. Unknown factions ignore everyone.
+pub fn faction_reaction(my_faction: &str, their_faction: &str, raws: &RawMaster) -> Reaction {
+    match raws.faction_index.get(my_faction) {
+        Some(reactions) => *reactions
+            .get(their_faction)
+            .or_else(|| reactions.get("Default"))
+            .unwrap_or(&Reaction::Ignore),
+        None => Reaction::Ignore,
+    }
+}
+
+/// What a mob of the given name rolls for damage, if the raws know of it
+pub fn mob_damage(name: &str, raws: &RawMaster) -> Option<Dice> {
+    let mob = &raws.raws.mobs[*raws.mob_index.get(name)?];
+    Dice::parse(&mob.stats.damage)
+}
+
+pub fn get_schools(raws: &RawMaster) -> &[School] {
+    &raws.raws.schools
+}
+
 pub fn get_spawn_table_for_depth(raws: &RawMaster, depth: i32) -> RandomTable {
     use super::SpawnTableEntry;
 
@@ -286,3 +533,26 @@ pub fn get_spawn_table_for_depth(raws: &RawMaster, depth: i32) -> RandomTable {
 
     rt
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{spawn_named_spell, RawMaster};
+    use crate::raws::parse_raws;
+    use crate::*;
+
+    #[test]
+    fn test_spawn_spells() {
+        let mut raws = RawMaster::empty();
+        raws.load(parse_raws());
+        let mut test_state = State { ecs: World::new() };
+        register_all(&mut test_state);
+
+        for spell in raws.raws.spells.iter() {
+            let hotkey = "1".to_string();
+            let entity = spawn_named_spell(&raws, test_state.ecs.create_entity(), &spell.name, hotkey);
+            assert!(entity.is_some());
+        }
+        let missing = spawn_named_spell(&raws, test_state.ecs.create_entity(), "Nope", "1".to_string());
+        assert!(missing.is_none());
+    }
+}
@@ -0,0 +1,11 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Deserialize, Debug)]
+pub struct School {
+    pub name: String,
+    pub description: String,
+    pub starting_spells: Vec<String>,
+    pub spells: Vec<String>,
+    pub stat_modifiers: Option<HashMap<String, i32>>,
+}
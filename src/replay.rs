@@ -0,0 +1,115 @@
+use crate::{PlayerAction, RunSeed, RunState};
+use rltk::console;
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::collections::VecDeque;
+use std::fs;
+
+const REPLAY_PATH: &str = "./replay.json";
+
+/// How long a replay lingers on each input, so there's time to see what it did
+pub const REPLAY_STEP_MS: f32 = 150.0;
+
+/// Anything the player decides while the game waits on them
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum PlayerInput {
+    Act(PlayerAction),
+    Target { x: i32, y: i32 },
+    CancelTarget,
+    LevelUp { choice: usize },
+}
+
+impl PlayerInput {
+    /// Whether this is something the game could be waiting for in the given state
+    pub fn fits(&self, runstate: RunState) -> bool {
+        matches!(
+            (runstate, self),
+            (RunState::AwaitingInput, PlayerInput::Act(_))
+                | (RunState::ShowTargeting { .. }, PlayerInput::Target { .. })
+                | (RunState::ShowTargeting { .. }, PlayerInput::CancelTarget)
+                | (RunState::LevelUp { .. }, PlayerInput::LevelUp { .. })
+        )
+    }
+}
+
+/// Everything needed to play a run again: how it started and every input since
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct ActionLog {
+    pub seed: RunSeed,
+    pub schools: [usize; 2],
+    pub inputs: Vec<PlayerInput>,
+}
+
+impl ActionLog {
+    pub fn new(seed: RunSeed, schools: [usize; 2]) -> ActionLog {
+        ActionLog {
+            seed,
+            schools,
+            inputs: Vec::new(),
+        }
+    }
+}
+
+/// Recorded inputs still waiting to be fed back to the game
+#[derive(Default)]
+pub struct Playback {
+    pub inputs: VecDeque<PlayerInput>,
+    pub wait_ms: f32,
+}
+
+impl Playback {
+    pub fn new(log: &ActionLog) -> Playback {
+        Playback {
+            inputs: log.inputs.iter().copied().collect(),
+            wait_ms: 0.0,
+        }
+    }
+}
+
+pub fn is_playing(ecs: &World) -> bool {
+    !ecs.fetch::<Playback>().inputs.is_empty()
+}
+
+/// Adds an input to the run's log. The generator is reseeded from the input's place in the
+/// log, so whatever follows doesn't depend on how the generator got here, a save and reload
+/// included.
+pub fn record(ecs: &mut World, input: PlayerInput) {
+    // Trips to the menu or looks around and through the inventory aren't part of the run
+    if matches!(
+        input,
+        PlayerInput::Act(PlayerAction::Menu)
+            | PlayerInput::Act(PlayerAction::Inventory)
+            | PlayerInput::Act(PlayerAction::Look)
+    ) {
+        return;
+    }
+    let seed = *ecs.fetch::<RunSeed>();
+    let index = {
+        let mut log = ecs.write_resource::<ActionLog>();
+        log.inputs.push(input);
+        log.inputs.len()
+    };
+    *ecs.write_resource::<rltk::RandomNumberGenerator>() = seed.input_rng(index);
+}
+
+pub fn save_log(log: &ActionLog) {
+    match serde_json::to_string(log) {
+        Ok(data) => {
+            if let Err(e) = fs::write(REPLAY_PATH, data) {
+                console::log(format!("WARNING - Unable to write replay: {}", e));
+            }
+        }
+        Err(e) => console::log(format!("WARNING - Unable to serialize replay: {}", e)),
+    }
+}
+
+pub fn load_log() -> Option<ActionLog> {
+    let data = fs::read_to_string(REPLAY_PATH).ok()?;
+    match serde_json::from_str(&data) {
+        Ok(log) => Some(log),
+        Err(e) => {
+            console::log(format!("WARNING - Unable to read replay: {}", e));
+            None
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use rltk::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+
+/// The seed a run was started with. Every level gets its own generator derived from it, so the
+/// same seed always builds and populates the same dungeon.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct RunSeed {
+    pub seed: u64,
+}
+
+impl RunSeed {
+    pub fn new(seed: u64) -> RunSeed {
+        RunSeed { seed }
+    }
+
+    pub fn random() -> RunSeed {
+        RunSeed::new(RandomNumberGenerator::new().next_u64())
+    }
+
+    pub fn level_rng(&self, depth: i32) -> RandomNumberGenerator {
+        // Spread consecutive depths far apart so neighbouring levels don't share a stream
+        RandomNumberGenerator::seeded(self.seed ^ (depth as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+    }
+
+    /// The generator for whatever follows the nth recorded input of the run
+    pub fn input_rng(&self, index: usize) -> RandomNumberGenerator {
+        RandomNumberGenerator::seeded(self.seed ^ (index as u64).wrapping_mul(0xD1B5_4A32_D192_ED03))
+    }
+}
@@ -0,0 +1,315 @@
+// Drives a game without a window. Scripted inputs stand in for the keyboard at the points where
+// the game would wait on it, and everything in between runs exactly as `State::tick` runs it.
+
+use crate::raws::{spawn_named_entity, SpawnType, RAWS};
+use crate::{
+    config, gamelog::GameLog, raws, register_all, systems, ActionLog, Behaviour, EntityStats,
+    InBackpack, KnownSpell, Map, Monster, Name, PlayerAction, PlayerInput, Position, RunSeed,
+    RunState, Spell, State, TileType, Viewshed,
+};
+use rltk::Point;
+use specs::prelude::*;
+
+/// The seed every quiet level starts from. What a test checks shouldn't hang on the layout, so
+/// changing it should only change the dice
+const QUIET_SEED: u64 = 1;
+/// How far the open floor of a quiet level reaches from the player, across and down
+const CLEARING: (i32, i32) = (8, 4);
+
+pub struct Simulation {
+    pub state: State,
+}
+
+impl Simulation {
+    /// Starts a seeded run with the named pair of schools, ready for the first input
+    pub fn new(seed: u64, schools: [&str; 2]) -> Simulation {
+        raws::load_raws();
+        let schools = {
+            let raws = RAWS.lock().unwrap();
+            let all_schools = raws::get_schools(&raws);
+            schools.map(|name| {
+                all_schools
+                    .iter()
+                    .position(|school| school.name == name)
+                    .unwrap_or_else(|| panic!("No school named {}", name))
+            })
+        };
+        Simulation::start(seed, schools)
+    }
+
+    /// Starts a run on the default seed with nothing else on the level, and an open floor all
+    /// around the player, so only what a test puts down can get in its way
+    pub fn quiet(schools: [&str; 2]) -> Simulation {
+        let mut sim = Simulation::new(QUIET_SEED, schools);
+        let monsters: Vec<Entity> = {
+            let entities = sim.state.ecs.entities();
+            let monsters = sim.state.ecs.read_storage::<Monster>();
+            let backpack = sim.state.ecs.read_storage::<InBackpack>();
+            let known_spells = sim.state.ecs.read_storage::<KnownSpell>();
+            let belongs = |owner: Entity| monsters.get(owner).is_some();
+            let carried = (&entities, &backpack)
+                .join()
+                .filter(|(_, pack)| belongs(pack.owner))
+                .map(|(e, _)| e);
+            let known = (&entities, &known_spells)
+                .join()
+                .filter(|(_, known)| belongs(known.owner))
+                .map(|(e, _)| e);
+            (&entities, &monsters)
+                .join()
+                .map(|(e, _)| e)
+                .chain(carried)
+                .chain(known)
+                .collect()
+        };
+        sim.ecs()
+            .delete_entities(&monsters)
+            .expect("Unable to delete monsters");
+
+        let pos = sim.player_pos();
+        {
+            let mut map = sim.state.ecs.write_resource::<Map>();
+            for y in (pos.y - CLEARING.1).max(1)..=(pos.y + CLEARING.1).min(map.height - 2) {
+                for x in (pos.x - CLEARING.0).max(1)..=(pos.x + CLEARING.0).min(map.width - 2) {
+                    let idx = map.xy_idx(x, y);
+                    if map.tiles[idx] == TileType::Wall {
+                        map.tiles[idx] = TileType::Floor;
+                    }
+                }
+            }
+        }
+        let player = sim.player();
+        if let Some(viewshed) = sim.state.ecs.write_storage::<Viewshed>().get_mut(player) {
+            viewshed.dirty = true;
+        }
+        sim.state.run_systems();
+        sim
+    }
+
+    fn start(seed: u64, schools: [usize; 2]) -> Simulation {
+        let mut state = State { ecs: World::new() };
+        register_all(&mut state);
+        raws::load_raws();
+        state.ecs.insert(Map::new(1, 1, 1));
+        state.ecs.insert(GameLog {
+            entries: Vec::new(),
+        });
+        state.ecs.insert(systems::particle::ParticleBuilder::new());
+        state.ecs.insert(RunSeed::new(seed));
+        state.ecs.insert(RunState::PreRun { schools });
+
+        let mut sim = Simulation { state };
+        sim.settle(RunState::PreRun { schools });
+        sim
+    }
+
+    /// Plays a recorded run from the top
+    pub fn replay(log: &ActionLog) -> Simulation {
+        let mut sim = Simulation::start(log.seed.seed, log.schools);
+        for input in log.inputs.iter() {
+            sim.input(*input);
+        }
+        sim
+    }
+
+    pub fn ecs(&mut self) -> &mut World {
+        &mut self.state.ecs
+    }
+
+    pub fn runstate(&self) -> RunState {
+        *self.state.ecs.fetch::<RunState>()
+    }
+
+    pub fn player(&self) -> Entity {
+        *self.state.ecs.fetch::<Entity>()
+    }
+
+    pub fn player_pos(&self) -> Point {
+        *self.state.ecs.fetch::<Point>()
+    }
+
+    pub fn log(&self) -> Vec<String> {
+        self.state.ecs.fetch::<GameLog>().entries.clone()
+    }
+
+    pub fn hit_points(&self, entity: Entity) -> i32 {
+        self.state
+            .ecs
+            .read_storage::<EntityStats>()
+            .get(entity)
+            .map_or(0, |stats| stats.get("hit_points").0)
+    }
+
+    pub fn set_current(&mut self, entity: Entity, stat: &str, value: i32) {
+        self.state
+            .ecs
+            .write_storage::<EntityStats>()
+            .get_mut(entity)
+            .unwrap_or_else(|| panic!("{:?} has no stats", entity))
+            .set_current(stat, value);
+    }
+
+    pub fn behaviour(&self, entity: Entity) -> Behaviour {
+        let behaviours = self.state.ecs.read_storage::<Behaviour>();
+        behaviours.get(entity).unwrap().clone()
+    }
+
+    pub fn distance_to_player(&self, entity: Entity) -> f32 {
+        let positions = self.state.ecs.read_storage::<Position>();
+        let pos = positions.get(entity).unwrap();
+        rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), self.player_pos())
+    }
+
+    pub fn position(&self, entity: Entity) -> Option<Point> {
+        let positions = self.state.ecs.read_storage::<Position>();
+        positions.get(entity).map(|pos| Point::new(pos.x, pos.y))
+    }
+
+    pub fn named(&self, name: &str) -> Option<Entity> {
+        let entities = self.state.ecs.entities();
+        let names = self.state.ecs.read_storage::<Name>();
+        (&entities, &names)
+            .join()
+            .find(|(_, n)| n.name == name)
+            .map(|(entity, _)| entity)
+    }
+
+    pub fn carried_by(&self, owner: Entity) -> Vec<Entity> {
+        let entities = self.state.ecs.entities();
+        let backpack = self.state.ecs.read_storage::<InBackpack>();
+        (&entities, &backpack)
+            .join()
+            .filter(|(_, pack)| pack.owner == owner)
+            .map(|(item, _)| item)
+            .collect()
+    }
+
+    /// Which hotkey index the player's spell of that name sits on
+    pub fn spell_hotkey(&self, name: &str) -> usize {
+        let spells = self.state.ecs.read_storage::<Spell>();
+        let names = self.state.ecs.read_storage::<Name>();
+        let hotkey = (&spells, &names)
+            .join()
+            .find(|(_, n)| n.name == name)
+            .map(|(spell, _)| spell.hotkey.clone())
+            .expect("The player doesn't know that spell");
+        config::CONFIG
+            .hotkeys()
+            .iter()
+            .position(|key| *key == hotkey)
+            .unwrap()
+    }
+
+    pub fn cast_at(&mut self, spell: &str, x: i32, y: i32) {
+        let index = self.spell_hotkey(spell);
+        self.input(PlayerInput::Act(PlayerAction::Hotkey { index }));
+        self.input(PlayerInput::Target { x, y });
+    }
+
+    /// Puts a named raw into the world and indexes it, as if it had been there all along
+    pub fn spawn(&mut self, name: &str, x: i32, y: i32) -> Entity {
+        let entity = spawn_named_entity(
+            &RAWS.lock().unwrap(),
+            self.state.ecs.create_entity(),
+            name,
+            SpawnType::AtPosition { x, y },
+        )
+        .unwrap_or_else(|| panic!("No raw named {}", name));
+        self.state.run_systems();
+        entity
+    }
+
+    /// Moves the player onto the first tile of the given type, returning where that is
+    pub fn stand_on(&mut self, tile: TileType) -> Point {
+        let (x, y) = {
+            let map = self.state.ecs.fetch::<Map>();
+            let idx = map
+                .tiles
+                .iter()
+                .position(|t| *t == tile)
+                .unwrap_or_else(|| panic!("There's no {:?} on this level", tile));
+            map.idx_xy(idx as i32)
+        };
+        let player = self.player();
+        self.state
+            .ecs
+            .write_storage::<Position>()
+            .insert(player, Position { x, y })
+            .expect("Unable to move the player");
+        *self.state.ecs.write_resource::<Point>() = Point::new(x, y);
+        Point::new(x, y)
+    }
+
+    /// Feeds one input to whatever the game is waiting on, then plays on until it waits again
+    pub fn input(&mut self, input: PlayerInput) -> RunState {
+        let runstate = self.runstate();
+        assert!(
+            input.fits(runstate),
+            "{:?} doesn't fit what the game is waiting for",
+            input
+        );
+        if runstate == RunState::AwaitingInput {
+            self.state.run_systems();
+        }
+        let next = self.state.apply_input(runstate, input);
+        self.settle(next)
+    }
+
+    /// Runs every state that doesn't need the player, one `State::tick` frame at a time
+    fn settle(&mut self, mut runstate: RunState) -> RunState {
+        runstate = self.state.end_frame(runstate);
+        loop {
+            // Systems check the stored state, just as they would on the next frame
+            *self.state.ecs.write_resource::<RunState>() = runstate;
+            match runstate {
+                RunState::PreRun { .. }
+                | RunState::PlayerTurn
+                | RunState::Ticking
+                | RunState::NextLevel
+                | RunState::PreviousLevel => {
+                    runstate = self.state.advance(runstate);
+                    runstate = self.state.end_frame(runstate);
+                }
+                _ => break,
+            }
+        }
+        runstate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_runs_repeat() {
+        let play = || {
+            let mut sim = Simulation::new(5, ["Translocation", "Abjuration"]);
+            for _ in 0..20 {
+                sim.input(PlayerInput::Act(PlayerAction::Wait));
+            }
+            (sim.player_pos(), sim.log())
+        };
+        assert_eq!(play(), play());
+    }
+
+    #[test]
+    fn test_replay_matches_run() {
+        let mut sim = Simulation::new(6, ["Evocation", "Pyromancy"]);
+        let moves = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+        for i in 0..12 {
+            let (dx, dy) = moves[i % moves.len()];
+            sim.input(PlayerInput::Act(PlayerAction::Move { dx, dy }));
+            sim.input(PlayerInput::Act(PlayerAction::Wait));
+        }
+        let log = (*sim.ecs().fetch::<ActionLog>()).clone();
+        assert_eq!(log.inputs.len(), 24);
+
+        let data = serde_json::to_string(&log).unwrap();
+        let log: ActionLog = serde_json::from_str(&data).unwrap();
+        let replayed = Simulation::replay(&log);
+        assert_eq!(replayed.player_pos(), sim.player_pos());
+        assert_eq!(replayed.log(), sim.log());
+        assert!(*replayed.state.ecs.fetch::<ActionLog>() == log);
+    }
+}
@@ -1,42 +1,31 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use super::rect::Rect;
 use super::{components, EntityStats, Name, Player, Position, Renderable, Viewshed, COLORS};
+use crate::dice::Dice;
 use crate::raws::{get_spawn_table_for_depth, spawn_named_entity, SpawnType, RAWS};
+use crate::systems::initiative::ACTION_COST;
 use crate::systems::random_table::RandomTable;
 use crate::Map;
 use rltk::RandomNumberGenerator;
 use specs::prelude::*;
 use specs::saveload::SimpleMarker;
 
-use super::map_builders::common::in_bounds;
 pub use components::*;
 use specs::saveload::MarkedBuilder;
 
 const MAX_SPAWNS: i32 = 4;
-
-pub fn spawn_room(ecs: &mut World, room: &Rect, map_depth: i32) {
-    let mut possible_targets: Vec<usize> = Vec::new();
-    {
-        // Borrow scope - to keep access to the map separated
-        let map = ecs.fetch::<Map>();
-        for y in room.y1 + 1..room.y2 {
-            for x in room.x1 + 1..room.x2 {
-                let idx = map.xy_idx(x, y);
-                if in_bounds(&map, idx) {
-                    possible_targets.push(idx);
-                }
-            }
-        }
-    }
-
-    spawn_region(ecs, &possible_targets, map_depth);
-}
+/// The player fights bare-handed, or near enough
+pub const PLAYER_DAMAGE: Dice = Dice {
+    count: 1,
+    sides: 6,
+    bonus: 1,
+};
 
 /// Fills a region with stuff!
 pub fn spawn_region(ecs: &mut World, area: &[usize], map_depth: i32) {
     let spawn_table = room_table(map_depth);
-    let mut spawn_points: HashMap<usize, String> = HashMap::new();
+    // Ordered, so that a seeded run spawns the same way every time
+    let mut spawn_points: BTreeMap<usize, String> = BTreeMap::new();
     let mut areas: Vec<usize> = Vec::from(area);
 
     // Scope to keep the borrow checker happy
@@ -100,9 +89,14 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
         .with(Name {
             name: "Player".to_string(),
         })
+        .with(components::Faction {
+            name: "Player".to_string(),
+        })
         .with(EntityStats {
             defense: 2,
             power: 5,
+            damage: PLAYER_DAMAGE,
+            level: 1,
             pools: HashMap::from([
                 (
                     "hit_points".to_string(),
@@ -118,8 +112,19 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
                         current: 10,
                     },
                 ),
+                (
+                    "xp".to_string(),
+                    Pool {
+                        max: experience_for_level(1),
+                        current: 0,
+                    },
+                ),
             ]),
         })
+        .with(Initiative {
+            energy: 0,
+            speed: ACTION_COST,
+        })
         .marked::<SimpleMarker<SerializeMe>>()
         .build()
 }
@@ -130,23 +135,24 @@ fn room_table(depth: i32) -> RandomTable {
 
 #[cfg(test)]
 mod tests {
-    use super::spawn_room;
+    use super::spawn_region;
+    use crate::map_builders::common::room_tiles;
     use crate::*;
 
     #[test]
     fn test_room() {
         // TODO: this goes in a setup function
         let mut test_state = State { ecs: World::new() };
-        test_state.ecs.insert(rltk::RandomNumberGenerator::new());
-        test_state.ecs.register::<Renderable>();
+        register_all(&mut test_state);
 
         let map = Map::new(1, 64, 64);
+        let new_room = rect::Rect::new(1, 1, 10, 10);
+        let area = room_tiles(&map, &new_room);
         test_state.ecs.insert(map);
 
-        let new_room = rect::Rect::new(1, 1, 10, 10);
         let depth = 0;
         for _i in 0..100 {
-            spawn_room(&mut test_state.ecs, &new_room, depth);
+            spawn_region(&mut test_state.ecs, &area, depth);
         }
 
         let num_ent = test_state.ecs.entities().join().count();
@@ -1,10 +1,11 @@
-use super::save_load;
-use crate::{effects::*, EntityStats, GameLog, Name, Player};
-use rltk::console;
+use crate::{effects::*, EntityStats, GameLog, InBackpack, KnownSpell, Name, Player, Position};
 use specs::prelude::*;
 
-pub fn delete_the_dead(ecs: &mut World) {
+/// Clears away dead monsters, returning whether the player is among the dead. What happens then
+/// is up to the caller.
+pub fn delete_the_dead(ecs: &mut World) -> bool {
     let mut dead: Vec<Entity> = Vec::new();
+    let mut player_died = false;
     // Using a scope to make the borrow checker happy
     {
         let combat_stats = ecs.read_storage::<EntityStats>();
@@ -24,19 +25,93 @@ pub fn delete_the_dead(ecs: &mut World) {
                         }
                         dead.push(entity);
                         if let Some(tile_idx) = entity_position(ecs, entity) {
-                            add_effect(None, EffectType::Bloodstain, Targets::Tile { tile_idx });
+                            add_effect(
+                                ecs,
+                                None,
+                                EffectType::Bloodstain,
+                                Targets::Tile { tile_idx },
+                            );
                         }
                     }
-                    Some(_) => {
-                        console::log("You are dead");
-                        save_load::delete_save();
-                        ::std::process::exit(0);
-                    }
+                    Some(_) => player_died = true,
                 }
             }
         }
     }
     for victim in dead {
+        drop_belongings(ecs, victim);
         ecs.delete_entity(victim).expect("Unable to delete");
     }
+    player_died
+}
+
+/// Leaves whatever a monster carried on the floor where it fell, and forgets its spells
+fn drop_belongings(ecs: &mut World, victim: Entity) {
+    let fell_at = match ecs.read_storage::<Position>().get(victim) {
+        Some(pos) => Position { x: pos.x, y: pos.y },
+        None => return,
+    };
+    let entities = ecs.entities();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut backpack = ecs.write_storage::<InBackpack>();
+    let known_spells = ecs.read_storage::<KnownSpell>();
+
+    let carried: Vec<Entity> = (&entities, &backpack)
+        .join()
+        .filter(|(_, pack)| pack.owner == victim)
+        .map(|(item, _)| item)
+        .collect();
+    for item in carried {
+        backpack.remove(item);
+        positions
+            .insert(item, fell_at.clone())
+            .expect("Unable to insert position");
+    }
+    for (spell, known) in (&entities, &known_spells).join() {
+        if known.owner == victim {
+            entities.delete(spell).expect("Unable to delete");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::Simulation;
+    use crate::{PlayerAction, PlayerInput, RunState};
+    use specs::prelude::*;
+
+    #[test]
+    fn test_player_death_ends_the_run() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let player = sim.player();
+        let pos = sim.player_pos();
+        sim.spawn("Troll", pos.x + 1, pos.y);
+        sim.set_current(player, "hit_points", 1);
+
+        let mut runstate = sim.runstate();
+        for _ in 0..20 {
+            runstate = sim.input(PlayerInput::Act(PlayerAction::Wait));
+            if runstate == RunState::GameOver {
+                break;
+            }
+        }
+        // The run ends, but what to do about it is left to the game
+        assert!(runstate == RunState::GameOver);
+        assert!(sim.runstate() == RunState::GameOver);
+        assert!(sim.ecs().is_alive(player));
+    }
+
+    #[test]
+    fn test_dead_monster_drops_items() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let pos = sim.player_pos();
+        let orc = sim.spawn("Orc", pos.x + 1, pos.y);
+        let potion = sim.carried_by(orc)[0];
+        sim.set_current(orc, "hit_points", 1);
+
+        sim.input(PlayerInput::Act(PlayerAction::Move { dx: 1, dy: 0 }));
+        assert!(!sim.ecs().is_alive(orc));
+        let dropped = sim.position(potion).expect("The potion wasn't dropped");
+        assert_eq!(dropped, rltk::Point::new(pos.x + 1, pos.y));
+    }
 }
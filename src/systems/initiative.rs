@@ -0,0 +1,114 @@
+use crate::{Initiative, MyTurn, RunState, StatusEffects, StatusType};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+
+/// Energy an entity spends to take one action. An entity with speed equal to this acts once per
+/// round, double it acts twice, half of it acts every other round.
+pub const ACTION_COST: i32 = 100;
+
+/// How many turns the player has taken this run
+#[derive(PartialEq, Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TurnCounter {
+    pub turns: u32,
+}
+
+pub fn effective_speed(initiative: &Initiative, statuses: Option<&StatusEffects>) -> i32 {
+    let mut speed = initiative.speed;
+    if let Some(statuses) = statuses {
+        if statuses.has(StatusType::Haste) {
+            speed *= 2;
+        }
+        if statuses.has(StatusType::Slow) {
+            speed /= 2;
+        }
+    }
+    i32::max(speed, 1)
+}
+
+// Hands out turns. If nobody has the energy to act, time passes and everyone gains energy
+// according to their speed. Everyone who can afford an action then gets a MyTurn.
+pub struct InitiativeSystem {}
+
+impl<'a> System<'a> for InitiativeSystem {
+    type SystemData = (
+        ReadExpect<'a, RunState>,
+        Entities<'a>,
+        WriteStorage<'a, Initiative>,
+        WriteStorage<'a, MyTurn>,
+        ReadStorage<'a, StatusEffects>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (runstate, entities, mut initiatives, mut turns, statuses) = data;
+
+        if *runstate != RunState::Ticking {
+            return;
+        }
+
+        if !initiatives.join().any(|i| i.energy >= ACTION_COST) {
+            for (entity, initiative) in (&entities, &mut initiatives).join() {
+                initiative.energy += effective_speed(initiative, statuses.get(entity));
+            }
+        }
+
+        for (entity, initiative) in (&entities, &mut initiatives).join() {
+            if initiative.energy >= ACTION_COST {
+                initiative.energy -= ACTION_COST;
+                turns
+                    .insert(entity, MyTurn {})
+                    .expect("Unable to insert turn");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{register_all, State};
+
+    fn turns_taken(speeds: &[i32], rounds: i32) -> Vec<i32> {
+        let mut state = State { ecs: World::new() };
+        register_all(&mut state);
+        state.ecs.insert(RunState::Ticking);
+        let actors: Vec<Entity> = speeds
+            .iter()
+            .map(|speed| {
+                state
+                    .ecs
+                    .create_entity()
+                    .with(Initiative {
+                        energy: 0,
+                        speed: *speed,
+                    })
+                    .build()
+            })
+            .collect();
+
+        // Run until the pacer is about to start round number rounds + 1
+        let mut taken = vec![0; actors.len()];
+        let pacer = actors[0];
+        loop {
+            InitiativeSystem {}.run_now(&state.ecs);
+            let mut turns = state.ecs.write_storage::<MyTurn>();
+            if turns.get(pacer).is_some() && taken[0] == rounds {
+                return taken;
+            }
+            for (i, actor) in actors.iter().enumerate() {
+                if turns.get(*actor).is_some() {
+                    taken[i] += 1;
+                }
+            }
+            turns.clear();
+        }
+    }
+
+    #[test]
+    fn test_speed_scales_turns() {
+        // the first speed paces the test: everyone else is measured against its turns
+        let taken = turns_taken(&[ACTION_COST, ACTION_COST * 2, ACTION_COST / 2], 10);
+        assert_eq!(taken[0], 10);
+        assert_eq!(taken[1], 20);
+        assert_eq!(taken[2], 5);
+    }
+}
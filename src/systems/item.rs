@@ -1,6 +1,7 @@
 use crate::{
     camera, effects::*, gamelog::GameLog, map::Map, AreaOfEffect, Cursor, InBackpack, Name,
-    Position, Ranged, RunState, WantsToPickupItem, WantsToUseItem,
+    OnLevel, Position, Ranged, RunState, WantsToDropItem, WantsToPickupItem, WantsToThrowItem,
+    WantsToUseItem,
 };
 use rltk::Point;
 use specs::prelude::*;
@@ -16,14 +17,24 @@ impl<'a> System<'a> for ItemCollection {
         WriteStorage<'a, Position>,
         ReadStorage<'a, Name>,
         WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, OnLevel>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) =
-            data;
+        let (
+            player_entity,
+            mut gamelog,
+            mut wants_pickup,
+            mut positions,
+            names,
+            mut backpack,
+            mut on_level,
+        ) = data;
 
         for pickup in wants_pickup.join() {
             positions.remove(pickup.item);
+            // Whoever picked it up takes it with them
+            on_level.remove(pickup.item);
             backpack
                 .insert(
                     pickup.item,
@@ -45,33 +56,170 @@ impl<'a> System<'a> for ItemCollection {
     }
 }
 
+/// How far anything can be thrown
+pub const THROW_RANGE: i32 = 6;
+
+pub struct ItemDrop {}
+
+impl<'a> System<'a> for ItemDrop {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToDropItem>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, OnLevel>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            entities,
+            mut wants_drop,
+            mut positions,
+            names,
+            mut backpack,
+            mut on_level,
+        ) = data;
+
+        for (entity, to_drop) in (&entities, &wants_drop).join() {
+            let dropped_at = match positions.get(entity) {
+                Some(pos) => Position { x: pos.x, y: pos.y },
+                None => continue,
+            };
+            positions
+                .insert(to_drop.item, dropped_at)
+                .expect("Unable to insert position");
+            // Left lying on the floor, it's part of the level again
+            on_level
+                .insert(to_drop.item, OnLevel {})
+                .expect("Unable to insert level");
+            backpack.remove(to_drop.item);
+
+            if entity == *player_entity {
+                gamelog.entries.push(format!(
+                    "You drop the {}.",
+                    names.get(to_drop.item).unwrap().name
+                ));
+            }
+        }
+
+        wants_drop.clear();
+    }
+}
+
+pub struct ItemThrow {}
+
+impl<'a> System<'a> for ItemThrow {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToThrowItem>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, OnLevel>,
+        ReadExpect<'a, Map>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            entities,
+            mut wants_throw,
+            mut positions,
+            names,
+            mut backpack,
+            mut on_level,
+            map,
+        ) = data;
+
+        for (entity, throw) in (&entities, &wants_throw).join() {
+            let item = throw.item;
+            let landing = match positions.get(entity) {
+                Some(from) => landing_spot(&map, Point::new(from.x, from.y), throw.target),
+                None => throw.target,
+            };
+            positions
+                .insert(
+                    item,
+                    Position {
+                        x: landing.x,
+                        y: landing.y,
+                    },
+                )
+                .expect("Unable to insert position");
+            on_level
+                .insert(item, OnLevel {})
+                .expect("Unable to insert level");
+            backpack.remove(item);
+
+            if entity == *player_entity {
+                gamelog
+                    .entries
+                    .push(format!("You throw the {}.", names.get(item).unwrap().name));
+            }
+        }
+
+        wants_throw.clear();
+    }
+}
+
+/// Where a thrown item comes down. It flies along the line to the target and drops on the last
+/// open tile before a wall or anything standing in the way.
+fn landing_spot(map: &Map, from: Point, target: Point) -> Point {
+    rltk::line2d(rltk::LineAlg::Bresenham, from, target)
+        .into_iter()
+        .filter(|pt| *pt != from)
+        .take_while(|pt| !map.blocked[map.xy_idx(pt.x, pt.y)])
+        .last()
+        .unwrap_or(from)
+}
+
 pub struct ItemUse {}
 
 impl<'a> System<'a> for ItemUse {
     #[allow(clippy::type_complexity)]
     type SystemData = (
         ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, EffectQueue>,
         Entities<'a>,
         WriteStorage<'a, WantsToUseItem>,
         ReadExpect<'a, Map>,
         ReadStorage<'a, AreaOfEffect>,
+        ReadStorage<'a, Name>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, entities, mut wants_use, map, aoe) = data;
+        let (player_entity, mut gamelog, mut effects, entities, mut wants_use, map, aoe, names) =
+            data;
 
         for (entity, useitem) in (&entities, &wants_use).join() {
-            add_effect(
+            // The player sees what they used in the inventory; monsters need pointing out
+            if entity != *player_entity {
+                if let (Some(user), Some(item)) = (names.get(entity), names.get(useitem.item)) {
+                    gamelog
+                        .entries
+                        .push(format!("{} uses the {}.", user.name, item.name));
+                }
+            }
+            effects.add(
                 Some(entity),
                 EffectType::ItemUse { item: useitem.item },
                 match useitem.target {
-                    None => Targets::Single {
-                        target: *player_entity,
-                    },
+                    None => Targets::Single { target: entity },
                     Some(target) => {
                         if let Some(aoe) = aoe.get(useitem.item) {
                             Targets::Tiles {
-                                tiles: aoe_tiles(&*map, target, aoe.radius),
+                                tiles: aoe_tiles(&map, target, aoe.radius),
                             }
                         } else {
                             Targets::Tile {
@@ -86,6 +234,18 @@ impl<'a> System<'a> for ItemUse {
     }
 }
 
+pub fn throw_item(ecs: &mut World, item: Entity) -> RunState {
+    let player_pos = ecs.fetch::<Point>();
+    let mut cursor = ecs.fetch_mut::<Cursor>();
+    cursor.point = camera::tile_to_screen(ecs, *player_pos);
+    RunState::ShowTargeting {
+        range: THROW_RANGE,
+        item,
+        radius: 0,
+        throwing: true,
+    }
+}
+
 pub fn use_item(ecs: &mut World, item: Entity) -> RunState {
     let is_aoe = ecs.read_storage::<AreaOfEffect>();
     let radius = match is_aoe.get(item) {
@@ -101,6 +261,7 @@ pub fn use_item(ecs: &mut World, item: Entity) -> RunState {
             range: ranged.range,
             item,
             radius,
+            throwing: false,
         };
     }
     let mut intent = ecs.write_storage::<WantsToUseItem>();
@@ -112,3 +273,91 @@ pub fn use_item(ecs: &mut World, item: Entity) -> RunState {
         .expect("Unable to insert intent");
     RunState::PlayerTurn
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::Simulation;
+    use crate::{
+        inventory_stacks, InBackpack, Map, OnLevel, PlayerAction, PlayerInput, RunState, TileType,
+    };
+    use rltk::Point;
+    use specs::prelude::*;
+
+    #[test]
+    fn test_pick_up_and_use_item() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let player = sim.player();
+        let pos = sim.player_pos();
+        sim.set_current(player, "hit_points", 10);
+        let potion = sim.spawn("Health Potion", pos.x, pos.y);
+
+        sim.input(PlayerInput::Act(PlayerAction::PickUp));
+        assert!(sim.ecs().read_storage::<InBackpack>().get(potion).is_some());
+
+        // Two starting spells take the first two hotkeys, so the potion sits on the third
+        sim.input(PlayerInput::Act(PlayerAction::Hotkey { index: 2 }));
+        assert!(!sim.ecs().is_alive(potion));
+        assert_eq!(sim.hit_points(player), 18);
+    }
+
+    #[test]
+    fn test_drop_and_throw() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let pos = sim.player_pos();
+        let potion = sim.spawn("Health Potion", pos.x, pos.y);
+        let scroll = sim.spawn("Magic Missile Scroll", pos.x, pos.y);
+        sim.input(PlayerInput::Act(PlayerAction::PickUp));
+        sim.input(PlayerInput::Act(PlayerAction::PickUp));
+        assert_eq!(inventory_stacks(&sim.state.ecs).len(), 2);
+
+        // Stacks go by name, so the potion comes first
+        sim.input(PlayerInput::Act(PlayerAction::DropItem { index: 0 }));
+        assert!(sim.ecs().read_storage::<InBackpack>().get(potion).is_none());
+        assert!(sim.ecs().read_storage::<OnLevel>().get(potion).is_some());
+        assert_eq!(sim.position(potion), Some(pos));
+        assert!(sim
+            .log()
+            .iter()
+            .any(|entry| entry == "You drop the Health Potion."));
+
+        let runstate = sim.input(PlayerInput::Act(PlayerAction::ThrowItem { index: 0 }));
+        assert!(matches!(
+            runstate,
+            RunState::ShowTargeting { throwing: true, .. }
+        ));
+        sim.input(PlayerInput::Target {
+            x: pos.x + 1,
+            y: pos.y,
+        });
+        assert_eq!(sim.position(scroll), Some(Point::new(pos.x + 1, pos.y)));
+        assert!(sim.ecs().read_storage::<OnLevel>().get(scroll).is_some());
+        assert!(inventory_stacks(&sim.state.ecs).is_empty());
+    }
+
+    #[test]
+    fn test_throws_stop_short() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let pos = sim.player_pos();
+        let throw_at = |sim: &mut Simulation, name: &str, x: i32, y: i32| {
+            let item = sim.spawn(name, pos.x, pos.y);
+            sim.input(PlayerInput::Act(PlayerAction::PickUp));
+            sim.input(PlayerInput::Act(PlayerAction::ThrowItem { index: 0 }));
+            sim.input(PlayerInput::Target { x, y });
+            sim.position(item)
+        };
+        {
+            let mut map = sim.ecs().write_resource::<Map>();
+            let idx = map.xy_idx(pos.x + 3, pos.y);
+            map.tiles[idx] = TileType::Wall;
+        }
+
+        // Nothing ends up inside the wall, it drops at the foot of it
+        let landed = throw_at(&mut sim, "Health Potion", pos.x + 3, pos.y);
+        assert_eq!(landed, Some(Point::new(pos.x + 2, pos.y)));
+
+        // Or in front of whoever is in the way
+        sim.spawn("Troll", pos.x, pos.y + 2);
+        let landed = throw_at(&mut sim, "Magic Missile Scroll", pos.x, pos.y + 3);
+        assert_eq!(landed, Some(Point::new(pos.x, pos.y + 1)));
+    }
+}
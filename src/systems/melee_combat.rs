@@ -1,5 +1,7 @@
-use crate::effects::{add_effect, EffectType, Targets};
+use crate::dice::{attack_roll, AttackRoll, Dice, Outcome, TO_HIT_DIE};
+use crate::effects::{EffectQueue, EffectType, Targets};
 use crate::{EntityStats, GameLog, Name, WantsToMelee};
+use rltk::RandomNumberGenerator;
 use specs::prelude::*;
 
 pub struct MeleeCombat {}
@@ -8,6 +10,8 @@ impl<'a> System<'a> for MeleeCombat {
     type SystemData = (
         Entities<'a>,
         WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, EffectQueue>,
         WriteStorage<'a, WantsToMelee>,
         ReadStorage<'a, Name>,
         ReadStorage<'a, EntityStats>,
@@ -15,7 +19,7 @@ impl<'a> System<'a> for MeleeCombat {
     //TODO: what's the diff btw WriteStorage and WriteExpect
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut log, mut wants_melee, names, entity_stats) = data;
+        let (entities, mut log, mut rng, mut effects, mut wants_melee, names, entity_stats) = data;
 
         for (entity, wants_melee, name, stats) in
             (&entities, &wants_melee, &names, &entity_stats).join()
@@ -25,19 +29,17 @@ impl<'a> System<'a> for MeleeCombat {
                 if target_stats.get("hit_points").0 > 0 {
                     let target_name = names.get(wants_melee.target).unwrap();
 
-                    let damage = i32::max(0, stats.power - target_stats.defense);
-
-                    if damage == 0 {
-                        log.entries.push(format!(
-                            "{} is unable to hurt {}",
-                            &name.name, &target_name.name
-                        ));
-                    } else {
-                        log.entries.push(format!(
-                            "{} hits {}, for {} hp.",
-                            &name.name, &target_name.name, damage
-                        ));
-                        add_effect(
+                    let (roll, damage) =
+                        roll_attack(&mut rng, stats.power, target_stats.defense, &stats.damage);
+                    log.entries.push(describe_attack(
+                        &name.name,
+                        "hits",
+                        &target_name.name,
+                        &roll,
+                        damage,
+                    ));
+                    if damage > 0 {
+                        effects.add(
                             Some(entity),
                             EffectType::Damage { amount: damage },
                             Targets::Single {
@@ -52,3 +54,69 @@ impl<'a> System<'a> for MeleeCombat {
         wants_melee.clear();
     }
 }
+
+/// Rolls to hit, and for damage if that lands. Critical hits roll their damage dice twice over.
+pub fn roll_attack(
+    rng: &mut RandomNumberGenerator,
+    power: i32,
+    defense: i32,
+    damage: &Dice,
+) -> (AttackRoll, i32) {
+    let roll = attack_roll(rng, power, defense);
+    let damage = match roll.outcome {
+        Outcome::Fumble | Outcome::Miss => 0,
+        Outcome::Hit => damage.roll(rng),
+        Outcome::Critical => damage.roll_critical(rng),
+    };
+    (roll, damage)
+}
+
+/// The log line for an attack, showing the roll. `verb` is what the attacker does on a hit.
+pub fn describe_attack(
+    attacker: &str,
+    verb: &str,
+    target: &str,
+    roll: &AttackRoll,
+    damage: i32,
+) -> String {
+    match roll.outcome {
+        Outcome::Fumble => format!("{} fumbles an attack on {} (natural 1).", attacker, target),
+        Outcome::Miss => format!(
+            "{} misses {} ({} vs {}).",
+            attacker, target, roll.total, roll.needed
+        ),
+        Outcome::Hit => format!(
+            "{} {} {} ({} vs {}), for {} hp.",
+            attacker, verb, target, roll.total, roll.needed, damage
+        ),
+        Outcome::Critical => format!(
+            "{} {} {} with a critical hit (natural {}), for {} hp.",
+            attacker, verb, target, TO_HIT_DIE, damage
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::Simulation;
+    use crate::{PlayerAction, PlayerInput};
+
+    #[test]
+    fn test_melee_combat() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let pos = sim.player_pos();
+        let goblin = sim.spawn("Goblin", pos.x + 1, pos.y);
+        let before = sim.hit_points(goblin);
+
+        sim.input(PlayerInput::Act(PlayerAction::Move { dx: 1, dy: 0 }));
+
+        assert_eq!(sim.player_pos(), pos);
+        assert!(sim.hit_points(goblin) < before);
+        let log = sim.log();
+        let hit = log
+            .iter()
+            .find(|entry| entry.starts_with("Player hits Goblin"));
+        // The log shows the roll that landed it
+        assert!(hit.unwrap().contains(" vs "));
+    }
+}
@@ -1,10 +1,14 @@
 pub mod damage;
+pub mod initiative;
 pub mod item;
 pub mod map_indexing;
 pub mod melee_combat;
 pub mod monster_ai;
+pub mod movement;
 pub mod particle;
 pub mod random_table;
+pub mod ranged_combat;
 pub mod save_load;
 pub mod spell;
+pub mod status;
 pub mod visibility;
@@ -1,73 +1,555 @@
-use crate::{Map, Monster, Position, RunState, Viewshed, WantsToMelee};
-use rltk::Point;
+use crate::raws::{faction_reaction, Reaction, ALLY_FACTION, DEFAULT_FACTION, RAWS};
+use crate::{
+    AiState, ApplyMove, AreaOfEffect, Behaviour, BehaviourKind, CostsMana, EntityStats, Faction,
+    InBackpack, InflictsDamage, InflictsStatus, KnownSpell, Map, Monster, MyTurn, Position,
+    ProvidesHealing, Ranged, RangedAttack, RunState, StatusEffects, StatusType, Viewshed,
+    WantsToCastSpell, WantsToMelee, WantsToShoot, WantsToUseItem,
+};
+use rltk::{Point, RandomNumberGenerator};
 use specs::prelude::*;
 
+/// Kiters back off when the player gets closer than this
+const KITE_DISTANCE: f32 = 3.0;
+/// Cowards run once their health drops to this share of the maximum
+const FLEE_BELOW: f32 = 0.34;
+/// Ambushers stay put until the player comes this close, or hurts them
+const AMBUSH_DISTANCE: f32 = 3.0;
+/// Guards give up on anything further than this from home
+const GUARD_RADIUS: f32 = 6.0;
+/// Monsters reach for a heal once their health drops to this share of the maximum
+const HEAL_BELOW: f32 = 0.5;
+/// Allies with nothing to fight catch up once the player gets further away than this
+const FOLLOW_DISTANCE: f32 = 2.5;
+
 pub struct MonsterAI {}
 
+enum Action {
+    Move(usize),
+    Melee(Entity),
+    Shoot(Entity),
+    UseItem(Entity, Option<Point>),
+    Cast(Entity, Option<Point>),
+}
+
+/// Something a monster can reach for instead of a weapon
+#[derive(Clone, Copy)]
+enum Tool {
+    Item(Entity),
+    Spell(Entity),
+}
+
+impl Tool {
+    fn entity(self) -> Entity {
+        match self {
+            Tool::Item(item) => item,
+            Tool::Spell(spell) => spell,
+        }
+    }
+
+    fn used_at(self, target: Option<Point>) -> Action {
+        match self {
+            Tool::Item(item) => Action::UseItem(item, target),
+            Tool::Spell(spell) => Action::Cast(spell, target),
+        }
+    }
+}
+
+type ToolStorage<'a> = (
+    ReadStorage<'a, InBackpack>,
+    ReadStorage<'a, KnownSpell>,
+    ReadStorage<'a, ProvidesHealing>,
+    ReadStorage<'a, Ranged>,
+    ReadStorage<'a, AreaOfEffect>,
+    ReadStorage<'a, CostsMana>,
+    ReadStorage<'a, InflictsDamage>,
+    ReadStorage<'a, InflictsStatus>,
+);
+
 impl<'a> System<'a> for MonsterAI {
     #[allow(clippy::type_complexity)]
     type SystemData = (
-        WriteExpect<'a, Map>,
+        ReadExpect<'a, Map>,
         ReadExpect<'a, Point>,
-        ReadExpect<'a, Entity>,
         ReadExpect<'a, RunState>,
         Entities<'a>,
-        WriteStorage<'a, Viewshed>,
+        ReadStorage<'a, Viewshed>,
         ReadStorage<'a, Monster>,
-        WriteStorage<'a, Position>,
+        ReadStorage<'a, Position>,
         WriteStorage<'a, WantsToMelee>,
+        WriteStorage<'a, WantsToShoot>,
+        ReadStorage<'a, StatusEffects>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        ReadStorage<'a, MyTurn>,
+        WriteStorage<'a, ApplyMove>,
+        WriteStorage<'a, Behaviour>,
+        ReadStorage<'a, RangedAttack>,
+        ReadStorage<'a, EntityStats>,
+        WriteStorage<'a, WantsToUseItem>,
+        WriteStorage<'a, WantsToCastSpell>,
+        ReadStorage<'a, Faction>,
+        ToolStorage<'a>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
-            mut map,
+            map,
             player_pos,
-            player_entity,
             runstate,
             entities,
-            mut viewshed,
+            viewshed,
             monster,
-            mut position,
+            position,
             mut wants_to_melee,
+            mut wants_to_shoot,
+            status_effects,
+            mut rng,
+            turns,
+            mut moves,
+            mut behaviours,
+            ranged_attacks,
+            entity_stats,
+            mut wants_to_use,
+            mut wants_to_cast,
+            factions,
+            tool_storage,
         ) = data;
 
-        if *runstate != RunState::MonsterTurn {
+        if *runstate != RunState::Ticking {
             return;
         }
+        let raws = RAWS.lock().unwrap();
 
-        for (entity, mut viewshed, _monster, mut pos) in
-            (&entities, &mut viewshed, &monster, &mut position).join()
+        for (entity, viewshed, _monster, pos, _turn, behaviour) in (
+            &entities,
+            &viewshed,
+            &monster,
+            &position,
+            &turns,
+            &mut behaviours,
+        )
+            .join()
         {
-            let distance =
-                rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
-            if distance < 1.1 {
-                //was 1.5 to allow diagonal attacks
-                //TODO: make disance a stat
-                wants_to_melee
-                    .insert(
-                        entity,
-                        WantsToMelee {
-                            target: *player_entity,
-                        },
-                    )
-                    .expect("Unable to insert attack");
-            } else if viewshed.visible_tiles.contains(&*player_pos) {
-                // Path to the player
-                let path = rltk::a_star_search(
-                    map.xy_idx(pos.x, pos.y),
-                    map.xy_idx(player_pos.x, player_pos.y),
-                    &*map,
-                );
-                if path.success && path.steps.len() > 1 {
-                    let mut idx = map.xy_idx(pos.x, pos.y);
-                    map.blocked[idx] = false;
-
-                    idx = path.steps[1];
-                    (pos.x, pos.y) = map.idx_xy(idx as i32);
-                    map.blocked[idx] = true;
-                    viewshed.dirty = true;
+            if let Some(effects) = status_effects.get(entity) {
+                if effects.skips_turn() {
+                    continue;
                 }
+                if effects.has(StatusType::Confusion) {
+                    // Stumble into a random neighbouring tile
+                    if let Some(dest_idx) = random_step(&map, &mut rng, pos) {
+                        moves
+                            .insert(entity, ApplyMove { dest_idx })
+                            .expect("Unable to insert move");
+                    }
+                    continue;
+                }
+            }
+
+            let here = Point::new(pos.x, pos.y);
+            let allegiance = |who: Entity| {
+                factions
+                    .get(who)
+                    .map(|faction| faction.allegiance(status_effects.get(who)))
+            };
+            let faction = allegiance(entity).unwrap_or(DEFAULT_FACTION);
+            let react = |other: Entity| match allegiance(other) {
+                Some(theirs) => faction_reaction(faction, theirs, &raws),
+                None => Reaction::Ignore,
+            };
+            // An ally keeps to the player, wherever they go
+            let ally = faction == ALLY_FACTION;
+            if ally {
+                behaviour.home = *player_pos;
+            }
+            let prey = nearest(&map, viewshed, here, entity, |other| {
+                react(other) == Reaction::Attack
+            });
+            let threat = nearest(&map, viewshed, here, entity, |other| {
+                react(other) == Reaction::Flee
+            });
+            if let Some(prey) = &prey {
+                behaviour.last_seen = Some(prey.at);
             }
+            let distance = prey.as_ref().map_or(f32::MAX, |prey| prey.distance);
+            let health = match entity_stats.get(entity) {
+                Some(stats) => {
+                    let (current, max) = stats.get("hit_points");
+                    current as f32 / max as f32
+                }
+                None => 1.0,
+            };
+            let mana = entity_stats
+                .get(entity)
+                .map_or(0, |stats| stats.get("mana").0);
+            let seen = prey.as_ref().map(|prey| prey.at);
+            behaviour.state = next_state(behaviour, seen, threat.is_some(), health, distance);
+
+            let tools = tools_of(&entities, &tool_storage, entity, mana);
+            let heal = match health <= HEAL_BELOW {
+                true => heal_with(&tool_storage, &tools),
+                false => None,
+            };
+            let action = match (behaviour.state, &prey) {
+                (AiState::Idle, _) if ally => {
+                    let behind = rltk::DistanceAlg::Pythagoras.distance2d(here, *player_pos);
+                    match behind > FOLLOW_DISTANCE {
+                        true => step_towards(&map, here, *player_pos).map(Action::Move),
+                        false => None,
+                    }
+                }
+                (AiState::Idle, _) => match behaviour.kind {
+                    BehaviourKind::Wanderer => random_step(&map, &mut rng, pos).map(Action::Move),
+                    BehaviourKind::Guard if here != behaviour.home => {
+                        step_towards(&map, here, behaviour.home).map(Action::Move)
+                    }
+                    _ => None,
+                },
+                (AiState::Hunting, Some(prey)) => {
+                    //TODO: make distance a stat
+                    let adjacent = distance < 1.1;
+                    let attack = attack_with(&tool_storage, &tools, distance);
+                    let shot = ranged_attacks.get(entity);
+                    let keeps_away = behaviour.kind == BehaviourKind::Kiter
+                        && (shot.is_some() || attack.is_some());
+                    let backing_off = match keeps_away && distance < KITE_DISTANCE {
+                        true => step_away(&map, here, prey.at),
+                        false => None,
+                    };
+                    match (heal, backing_off, attack, shot) {
+                        (Some(tool), _, _, _) => Some(tool.used_at(None)),
+                        (_, Some(dest_idx), _, _) => Some(Action::Move(dest_idx)),
+                        (_, _, Some(tool), _) => Some(tool.used_at(Some(prey.at))),
+                        _ if adjacent => Some(Action::Melee(prey.entity)),
+                        (_, _, _, Some(shot)) if distance <= shot.range as f32 => {
+                            Some(Action::Shoot(prey.entity))
+                        }
+                        _ => step_towards(&map, here, prey.at).map(Action::Move),
+                    }
+                }
+                (AiState::Hunting, None) => None,
+                (AiState::Searching, _) => {
+                    // Head for where the prey was last seen, and give up on getting there
+                    let step = behaviour
+                        .last_seen
+                        .and_then(|last_seen| step_towards(&map, here, last_seen));
+                    if step.is_none() {
+                        behaviour.state = AiState::Idle;
+                        behaviour.last_seen = None;
+                    }
+                    step.map(Action::Move)
+                }
+                (AiState::Fleeing, _) => {
+                    // Whatever it's afraid of, or whatever has hurt it so badly
+                    let danger = threat.as_ref().or(prey.as_ref());
+                    let escape = danger.and_then(|danger| step_away(&map, here, danger.at));
+                    match (heal, escape, danger) {
+                        (Some(tool), _, _) => Some(tool.used_at(None)),
+                        (None, Some(dest_idx), _) => Some(Action::Move(dest_idx)),
+                        // Cornered
+                        (None, None, Some(danger)) if danger.distance < 1.1 => {
+                            Some(Action::Melee(danger.entity))
+                        }
+                        (None, None, _) => None,
+                    }
+                }
+            };
+
+            match action {
+                Some(Action::Move(dest_idx)) => {
+                    moves
+                        .insert(entity, ApplyMove { dest_idx })
+                        .expect("Unable to insert move");
+                }
+                Some(Action::Melee(target)) => {
+                    wants_to_melee
+                        .insert(entity, WantsToMelee { target })
+                        .expect("Unable to insert attack");
+                }
+                Some(Action::Shoot(target)) => {
+                    wants_to_shoot
+                        .insert(entity, WantsToShoot { target })
+                        .expect("Unable to insert attack");
+                }
+                Some(Action::UseItem(item, target)) => {
+                    wants_to_use
+                        .insert(entity, WantsToUseItem { item, target })
+                        .expect("Unable to insert intent");
+                }
+                Some(Action::Cast(spell, target)) => {
+                    wants_to_cast
+                        .insert(entity, WantsToCastSpell { spell, target })
+                        .expect("Unable to insert intent");
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// Something a monster has its eye on
+struct Sighting {
+    entity: Entity,
+    at: Point,
+    distance: f32,
+}
+
+/// The closest thing in view, other than the monster itself, that it feels a certain way about
+fn nearest(
+    map: &Map,
+    viewshed: &Viewshed,
+    here: Point,
+    me: Entity,
+    wanted: impl Fn(Entity) -> bool,
+) -> Option<Sighting> {
+    viewshed
+        .visible_tiles
+        .iter()
+        .flat_map(|at| {
+            map.tile_content[map.xy_idx(at.x, at.y)]
+                .iter()
+                .map(move |entity| (*entity, *at))
+        })
+        .filter(|(entity, _)| *entity != me && wanted(*entity))
+        .map(|(entity, at)| Sighting {
+            entity,
+            at,
+            distance: rltk::DistanceAlg::Pythagoras.distance2d(here, at),
+        })
+        .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap())
+}
+
+/// Where a monster's head is at this turn, given where it can see its prey, if anywhere, whether
+/// anything it fears is in view, and how hurt it is
+fn next_state(
+    behaviour: &Behaviour,
+    seen: Option<Point>,
+    threatened: bool,
+    health: f32,
+    distance: f32,
+) -> AiState {
+    let kind = behaviour.kind;
+    if threatened {
+        return AiState::Fleeing;
+    }
+    if kind == BehaviourKind::Coward && health <= FLEE_BELOW {
+        if seen.is_some() {
+            return AiState::Fleeing;
         }
+        // Out of sight is safe enough
+        return AiState::Idle;
+    }
+
+    let in_reach = |target: Point| {
+        kind != BehaviourKind::Guard
+            || rltk::DistanceAlg::Pythagoras.distance2d(behaviour.home, target) <= GUARD_RADIUS
+    };
+    let lying_in_wait = kind == BehaviourKind::Ambusher
+        && behaviour.state == AiState::Idle
+        && distance > AMBUSH_DISTANCE
+        && health >= 1.0;
+    match (seen, behaviour.last_seen) {
+        (Some(_), _) if lying_in_wait => AiState::Idle,
+        (Some(prey), _) if in_reach(prey) => AiState::Hunting,
+        (Some(_), _) => AiState::Idle,
+        // Only something that was after its prey goes looking for it
+        (None, Some(last_seen))
+            if behaviour.state != AiState::Idle
+                && behaviour.state != AiState::Fleeing
+                && in_reach(last_seen) =>
+        {
+            AiState::Searching
+        }
+        (None, _) => AiState::Idle,
+    }
+}
+
+/// Whatever a monster carries, and the spells it has the mana for
+fn tools_of(entities: &Entities, storage: &ToolStorage, owner: Entity, mana: i32) -> Vec<Tool> {
+    let (backpack, known_spells, _, _, _, costs, _, _) = storage;
+    let items = (entities, backpack)
+        .join()
+        .filter(|(_, pack)| pack.owner == owner)
+        .map(|(item, _)| Tool::Item(item));
+    let spells = (entities, known_spells)
+        .join()
+        .filter(|(_, known)| known.owner == owner)
+        .map(|(spell, _)| Tool::Spell(spell));
+    items
+        .chain(spells)
+        .filter(|tool| match costs.get(tool.entity()) {
+            Some(cost) => cost.mana_amount <= mana,
+            None => true,
+        })
+        .collect()
+}
+
+fn heal_with(storage: &ToolStorage, tools: &[Tool]) -> Option<Tool> {
+    let (_, _, healing, ranged, _, _, _, _) = storage;
+    tools
+        .iter()
+        .copied()
+        .find(|tool| healing.get(tool.entity()).is_some() && ranged.get(tool.entity()).is_none())
+}
+
+/// Something that reaches the player from here without catching the user in the blast
+fn attack_with(storage: &ToolStorage, tools: &[Tool], distance: f32) -> Option<Tool> {
+    let (_, _, _, ranged, aoe, _, damage, status) = storage;
+    tools.iter().copied().find(|tool| {
+        let tool = tool.entity();
+        let in_range = ranged.get(tool).is_some_and(|r| distance <= r.range as f32);
+        let clear_of_blast = aoe
+            .get(tool)
+            .is_none_or(|aoe| (aoe.radius as f32) < distance);
+        let harmful = damage.get(tool).is_some() || status.get(tool).is_some();
+        in_range && clear_of_blast && harmful
+    })
+}
+
+/// The first step on the way to a tile. A* never reaches a blocked tile, so for another monster
+/// it aims at the open side of it closest to here instead
+fn step_towards(map: &Map, from: Point, to: Point) -> Option<usize> {
+    let to = match map.blocked[map.xy_idx(to.x, to.y)] {
+        true => [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .iter()
+            .map(|(dx, dy)| Point::new(to.x + dx, to.y + dy))
+            .filter(|side| *side == from || !map.blocked[map.xy_idx(side.x, side.y)])
+            .min_by(|a, b| {
+                let a = rltk::DistanceAlg::Pythagoras.distance2d(from, *a);
+                let b = rltk::DistanceAlg::Pythagoras.distance2d(from, *b);
+                a.partial_cmp(&b).unwrap()
+            })?,
+        false => to,
+    };
+    let path = rltk::a_star_search(map.xy_idx(from.x, from.y), map.xy_idx(to.x, to.y), map);
+    if path.success && path.steps.len() > 1 {
+        return Some(path.steps[1]);
+    }
+    None
+}
+
+/// The open neighbouring tile that puts the most ground between a monster and a threat
+fn step_away(map: &Map, from: Point, threat: Point) -> Option<usize> {
+    let current = rltk::DistanceAlg::Pythagoras.distance2d(from, threat);
+    [(-1, 0), (1, 0), (0, -1), (0, 1)]
+        .iter()
+        .map(|(dx, dy)| Point::new(from.x + dx, from.y + dy))
+        .filter(|to| !map.blocked[map.xy_idx(to.x, to.y)])
+        .map(|to| (rltk::DistanceAlg::Pythagoras.distance2d(to, threat), to))
+        .filter(|(distance, _)| *distance > current)
+        .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+        .map(|(_, to)| map.xy_idx(to.x, to.y))
+}
+
+fn random_step(map: &Map, rng: &mut RandomNumberGenerator, pos: &Position) -> Option<usize> {
+    let (dx, dy) = match rng.roll_dice(1, 4) {
+        1 => (-1, 0),
+        2 => (1, 0),
+        3 => (0, -1),
+        _ => (0, 1),
+    };
+    let dest_idx = map.xy_idx(pos.x + dx, pos.y + dy);
+    if map.blocked[dest_idx] {
+        return None;
+    }
+    Some(dest_idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::Simulation;
+    use crate::{AiState, PlayerAction, PlayerInput, TileType};
+
+    #[test]
+    fn test_coward_flees() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let pos = sim.player_pos();
+        let goblin = sim.spawn("Goblin", pos.x + 2, pos.y);
+        sim.set_current(goblin, "hit_points", 1);
+        let before = sim.distance_to_player(goblin);
+
+        sim.input(PlayerInput::Act(PlayerAction::Wait));
+        assert!(sim.behaviour(goblin).state == AiState::Fleeing);
+        assert!(sim.distance_to_player(goblin) > before);
+    }
+
+    #[test]
+    fn test_kiter_shoots() {
+        // Cryomancy's defense would turn the stones aside
+        let mut sim = Simulation::quiet(["Pyromancy", "Evocation"]);
+        let pos = sim.player_pos();
+        sim.spawn("Kobold Slinger", pos.x + 4, pos.y);
+
+        sim.input(PlayerInput::Act(PlayerAction::Wait));
+        assert!(sim
+            .log()
+            .iter()
+            .any(|entry| entry.starts_with("Kobold Slinger shoots Player")));
+    }
+
+    #[test]
+    fn test_lost_player_is_searched_for() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let pos = sim.player_pos();
+        let goblin = sim.spawn("Goblin", pos.x + 3, pos.y);
+        sim.input(PlayerInput::Act(PlayerAction::Wait));
+        assert!(sim.behaviour(goblin).state == AiState::Hunting);
+
+        // Slip away somewhere it can't see
+        let last_seen = sim.player_pos();
+        sim.stand_on(TileType::DownStairs);
+        sim.input(PlayerInput::Act(PlayerAction::Wait));
+        let goblin_ai = sim.behaviour(goblin);
+        assert!(goblin_ai.state == AiState::Searching);
+        assert!(goblin_ai.last_seen == Some(last_seen));
+    }
+
+    #[test]
+    fn test_ambusher_waits() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let pos = sim.player_pos();
+        let troll = sim.spawn("Troll", pos.x + 5, pos.y);
+        let before = sim.distance_to_player(troll);
+        for _ in 0..4 {
+            sim.input(PlayerInput::Act(PlayerAction::Wait));
+        }
+        assert!(sim.behaviour(troll).state == AiState::Idle);
+        assert_eq!(sim.distance_to_player(troll), before);
+    }
+
+    #[test]
+    fn test_hurt_monster_drinks_potion() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let pos = sim.player_pos();
+        let orc = sim.spawn("Orc", pos.x + 3, pos.y);
+        let player = sim.player();
+        assert_eq!(sim.carried_by(orc).len(), 1);
+        for hurt in [orc, player] {
+            sim.set_current(hurt, "hit_points", 4);
+        }
+
+        sim.input(PlayerInput::Act(PlayerAction::Wait));
+        assert!(sim
+            .log()
+            .iter()
+            .any(|entry| entry == "Orc uses the Health Potion."));
+        assert!(sim.hit_points(orc) > 4);
+        // The potion is the orc's, so it's the orc that gets better
+        assert_eq!(sim.hit_points(player), 4);
+        assert!(sim.carried_by(orc).is_empty());
+    }
+
+    #[test]
+    fn test_factions_fight_each_other() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Cryomancy"]);
+        let pos = sim.player_pos();
+        let goblin = sim.spawn("Goblin", pos.x + 4, pos.y);
+        sim.spawn("Kobold Slinger", pos.x + 5, pos.y);
+
+        sim.input(PlayerInput::Act(PlayerAction::Wait));
+        // The kobold is nearer than the player, and the goblin hates it just as much
+        assert!(sim
+            .log()
+            .iter()
+            .any(|entry| entry.starts_with("Goblin hits Kobold Slinger")));
+        assert!(sim.behaviour(goblin).state == AiState::Hunting);
     }
 }
@@ -0,0 +1,139 @@
+use crate::{map::Map, ApplyMove, ApplyTeleport, BlocksTile, Position, TileType, Viewshed};
+use rltk::Point;
+use specs::prelude::*;
+
+// Moves anything with a movement intent, keeping the map index and the player's Point in sync.
+// Teleports land even on an occupied tile, so several of them can swap entities around. Steps
+// are refused if someone got to the destination first.
+pub struct Movement {}
+
+impl<'a> System<'a> for Movement {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        WriteExpect<'a, Point>,
+        ReadExpect<'a, Entity>,
+        Entities<'a>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, BlocksTile>,
+        WriteStorage<'a, Viewshed>,
+        WriteStorage<'a, ApplyMove>,
+        WriteStorage<'a, ApplyTeleport>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            mut map,
+            mut player_pos,
+            player_entity,
+            entities,
+            mut positions,
+            blockers,
+            mut viewsheds,
+            mut moves,
+            mut teleports,
+        ) = data;
+
+        let mut movers: Vec<(Entity, usize)> = Vec::new();
+        for (entity, teleport) in (&entities, &teleports).join() {
+            let idx = map.xy_idx(teleport.dest_x, teleport.dest_y);
+            if idx < map.tile_count && map.tiles[idx] != TileType::Wall {
+                movers.push((entity, idx));
+            }
+        }
+        teleports.clear();
+
+        // Lift every teleporter off the map before placing any, so swaps don't trample each other
+        for (entity, _) in movers.iter() {
+            if let Some(pos) = positions.get(*entity) {
+                let idx = map.xy_idx(pos.x, pos.y);
+                map.tile_content[idx].retain(|e| e != entity);
+                if blockers.get(*entity).is_some() {
+                    map.blocked[idx] = false;
+                }
+            }
+        }
+        for (entity, idx) in movers.iter() {
+            place(&mut map, *entity, *idx, &mut positions, &blockers, &mut viewsheds);
+        }
+
+        for (entity, step) in (&entities, &moves).join() {
+            if map.blocked[step.dest_idx] {
+                continue;
+            }
+            if let Some(pos) = positions.get(entity) {
+                let idx = map.xy_idx(pos.x, pos.y);
+                map.tile_content[idx].retain(|e| *e != entity);
+                if blockers.get(entity).is_some() {
+                    map.blocked[idx] = false;
+                }
+            }
+            place(&mut map, entity, step.dest_idx, &mut positions, &blockers, &mut viewsheds);
+        }
+        moves.clear();
+
+        if let Some(pos) = positions.get(*player_entity) {
+            *player_pos = Point::new(pos.x, pos.y);
+        }
+    }
+}
+
+fn place(
+    map: &mut Map,
+    entity: Entity,
+    idx: usize,
+    positions: &mut WriteStorage<Position>,
+    blockers: &ReadStorage<BlocksTile>,
+    viewsheds: &mut WriteStorage<Viewshed>,
+) {
+    if let Some(pos) = positions.get_mut(entity) {
+        (pos.x, pos.y) = map.idx_xy(idx as i32);
+        map.tile_content[idx].push(entity);
+        if blockers.get(entity).is_some() {
+            map.blocked[idx] = true;
+        }
+        if let Some(viewshed) = viewsheds.get_mut(entity) {
+            viewshed.dirty = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{register_all, State};
+
+    #[test]
+    fn test_teleport_swap() {
+        let mut state = State { ecs: World::new() };
+        register_all(&mut state);
+        let mut map = Map::new(1, 10, 10);
+        map.tiles = vec![TileType::Floor; map.tile_count];
+        state.ecs.insert(map);
+
+        let a = state
+            .ecs
+            .create_entity()
+            .with(Position { x: 2, y: 2 })
+            .with(BlocksTile {})
+            .with(ApplyTeleport { dest_x: 5, dest_y: 5 })
+            .build();
+        let b = state
+            .ecs
+            .create_entity()
+            .with(Position { x: 5, y: 5 })
+            .with(BlocksTile {})
+            .with(ApplyTeleport { dest_x: 2, dest_y: 2 })
+            .build();
+        crate::systems::map_indexing::MapIndexing {}.run_now(&state.ecs);
+        Movement {}.run_now(&state.ecs);
+
+        let positions = state.ecs.read_storage::<Position>();
+        let map = state.ecs.fetch::<Map>();
+        assert_eq!((positions.get(a).unwrap().x, positions.get(a).unwrap().y), (5, 5));
+        assert_eq!((positions.get(b).unwrap().x, positions.get(b).unwrap().y), (2, 2));
+        assert!(map.blocked[map.xy_idx(2, 2)]);
+        assert!(map.blocked[map.xy_idx(5, 5)]);
+        assert_eq!(map.tile_content[map.xy_idx(5, 5)], vec![a]);
+    }
+}
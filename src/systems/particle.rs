@@ -1,4 +1,4 @@
-use crate::{ParticleLifetime, Position, Renderable, Rltk};
+use crate::{OnLevel, ParticleLifetime, Position, Renderable, Rltk};
 use rltk::RGB;
 use specs::prelude::*;
 
@@ -8,7 +8,7 @@ pub fn cull_dead_particles(ecs: &mut World, ctx: &Rltk) {
         // Age out particles
         let mut particles = ecs.write_storage::<ParticleLifetime>();
         let entities = ecs.entities();
-        for (entity, mut particle) in (&entities, &mut particles).join() {
+        for (entity, particle) in (&entities, &mut particles).join() {
             particle.lifetime_ms -= ctx.frame_time_ms;
             if particle.lifetime_ms < 0.0 {
                 dead_particles.push(entity);
@@ -70,11 +70,19 @@ impl<'a> System<'a> for ParticleSpawn {
         WriteStorage<'a, Position>,
         WriteStorage<'a, Renderable>,
         WriteStorage<'a, ParticleLifetime>,
+        WriteStorage<'a, OnLevel>,
         WriteExpect<'a, ParticleBuilder>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut positions, mut renderables, mut particles, mut particle_builder) = data;
+        let (
+            entities,
+            mut positions,
+            mut renderables,
+            mut particles,
+            mut on_level,
+            mut particle_builder,
+        ) = data;
         for new_particle in particle_builder.requests.iter() {
             let p = entities.create();
             positions
@@ -105,6 +113,9 @@ impl<'a> System<'a> for ParticleSpawn {
                     },
                 )
                 .expect("Unable to insert lifetime");
+            on_level
+                .insert(p, OnLevel {})
+                .expect("Unable to insert level");
         }
 
         particle_builder.requests.clear();
@@ -0,0 +1,58 @@
+use super::melee_combat::{describe_attack, roll_attack};
+use crate::effects::{EffectQueue, EffectType, Targets};
+use crate::{EntityStats, GameLog, Name, WantsToShoot};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+pub struct RangedCombat {}
+
+impl<'a> System<'a> for RangedCombat {
+    type SystemData = (
+        Entities<'a>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, EffectQueue>,
+        WriteStorage<'a, WantsToShoot>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, EntityStats>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut log, mut rng, mut effects, mut wants_shoot, names, entity_stats) = data;
+
+        for (entity, wants_shoot, name, stats) in
+            (&entities, &wants_shoot, &names, &entity_stats).join()
+        {
+            if stats.get("hit_points").0 < 1 {
+                continue;
+            }
+            let target_stats = match entity_stats.get(wants_shoot.target) {
+                Some(target_stats) if target_stats.get("hit_points").0 > 0 => target_stats,
+                _ => continue,
+            };
+            let target_name = names.get(wants_shoot.target).unwrap();
+
+            // Shots land the way blows do, just from further off
+            let (roll, damage) =
+                roll_attack(&mut rng, stats.power, target_stats.defense, &stats.damage);
+            log.entries.push(describe_attack(
+                &name.name,
+                "shoots",
+                &target_name.name,
+                &roll,
+                damage,
+            ));
+            if damage > 0 {
+                effects.add(
+                    Some(entity),
+                    EffectType::Damage { amount: damage },
+                    Targets::Single {
+                        target: wants_shoot.target,
+                    },
+                );
+            }
+        }
+
+        wants_shoot.clear();
+    }
+}
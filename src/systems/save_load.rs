@@ -1,112 +1,710 @@
 use super::super::components::*;
+use super::initiative::TurnCounter;
+use crate::dice::Dice;
+use rltk::console;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value};
 use specs::prelude::*;
 use specs::saveload::{
-    DeserializeComponents, MarkedBuilder, SerializeComponents, SimpleMarker, SimpleMarkerAllocator,
+    DeserializeComponents, MarkedBuilder, Marker, MarkerAllocator, SerializeComponents,
+    SimpleMarker, SimpleMarkerAllocator,
 };
 use std::convert::Infallible;
+use std::fmt;
 use std::fs;
-use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-macro_rules! serialize_individually {
-    ($ecs:expr, $ser:expr, $data:expr, $( $type:ty),*) => {
-        $(
-        SerializeComponents::<Infallible, SimpleMarker<SerializeMe>>::serialize(
-            &( $ecs.read_storage::<$type>(), ),
-            &$data.0,
-            &$data.1,
-            &mut $ser,
-        )
-        .unwrap();
-        )*
-    };
-}
-
-macro_rules! deserialize_individually {
-    ($ecs:expr, $de:expr, $data:expr, $( $type:ty),*) => {
-        $(
-        DeserializeComponents::<Infallible, _>::deserialize(
-            &mut ( &mut $ecs.write_storage::<$type>(), ),
-            &mut $data.0, // entities
-            &mut $data.1, // marker
-            &mut $data.2, // allocater
-            &mut $de,
-        )
-        .unwrap();
-        )*
-    };
-}
+const SAVE_DIR: &str = "./saves";
+// Where the one and only save lived before there were slots
+const LEGACY_SAVE_PATH: &str = "./savegame.json";
+pub const SAVE_SLOTS: usize = 5;
 
-pub fn save_game(ecs: &mut World) {
-    // Create helper
-    let mapcopy = ecs.get_mut::<super::super::map::Map>().unwrap().clone();
-    let savehelper = ecs
-        .create_entity()
-        .with(SerializationHelper { map: mapcopy })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+/// Bumped whenever a change to a saved component would trip up older saves. Each bump needs a
+/// matching entry in MIGRATIONS.
+pub const SAVE_VERSION: u32 = 1;
 
-    // Actually serialize
-    {
-        let data = (
-            ecs.entities(),
-            ecs.read_storage::<SimpleMarker<SerializeMe>>(),
-        );
+/// Brings a save from one version up to the next. The entry at index N takes version N to N+1.
+type Migration = fn(&mut SaveFile) -> Result<(), SaveError>;
+const MIGRATIONS: [Migration; SAVE_VERSION as usize] = [from_unversioned];
 
-        let writer = File::create("./savegame.json").unwrap();
-        let mut serializer = serde_json::Serializer::new(writer);
-        serialize_individually!(
-            ecs,
-            serializer,
-            data,
+/// Calls the given macro with every component that goes into a save. `register_all` goes
+/// through this list too, so a component can't be registered and then left out of saves.
+macro_rules! saved_components {
+    ($callback:ident, $($args:expr),*) => {
+        $callback!(
+            $($args),*,
             Position,
             Renderable,
             Player,
             Viewshed,
             Monster,
+            Behaviour,
+            RangedAttack,
+            KnownSpell,
             Name,
+            Description,
             BlocksTile,
             EntityStats,
             WantsToMelee,
+            WantsToShoot,
             Item,
             Consumable,
             Ranged,
             InflictsDamage,
+            Summons,
             AreaOfEffect,
             ProvidesHealing,
+            ProvidesMana,
+            CostsMana,
             InBackpack,
             WantsToPickupItem,
             WantsToUseItem,
             WantsToDropItem,
+            WantsToThrowItem,
             Cursor,
             ParticleLifetime,
             SerializationHelper,
+            Spell,
             WantsToCastSpell,
-            Antagonistic,
+            Faction,
+            Quips,
             SingleActivation,
             ApplyTeleport,
+            ApplyMove,
+            Pushes,
+            Pulls,
+            KnocksBack,
+            SwapsPlaces,
             TeleportTo,
             SpawnParticleLine,
             SpawnParticleBurst,
-            Hidden
+            Hidden,
+            OnLevel,
+            StatusEffects,
+            InflictsStatus,
+            CuresStatus,
+            MagicSchools,
+            GrantsExperience,
+            PendingLevelUp,
+            Initiative,
+            MyTurn
+        )
+    };
+}
+
+macro_rules! register_individually {
+    ($ecs:expr, $( $type:ty),*) => {
+        $(
+        $ecs.register::<$type>();
+        )*
+    };
+}
+
+// Each storage is kept under its component's name, so adding, removing or reordering
+// components doesn't shift anything else in the file
+macro_rules! serialize_individually {
+    ($ecs:expr, $components:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        $components.insert(
+            stringify!($type).to_string(),
+            SerializeComponents::<Infallible, SimpleMarker<SerializeMe>>::serialize(
+                &( $ecs.read_storage::<$type>(), ),
+                &$data.0,
+                &$data.1,
+                serde_json::value::Serializer,
+            )?,
         );
+        )*
+    };
+}
+
+macro_rules! deserialize_individually {
+    ($ecs:expr, $components:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        if let Some(storage) = $components.remove(stringify!($type)) {
+            DeserializeComponents::<Infallible, _>::deserialize(
+                &mut ( &mut $ecs.write_storage::<$type>(), ),
+                &mut $data.0, // entities
+                &mut $data.1, // marker
+                &mut $data.2, // allocater
+                storage,
+            )?;
+        }
+        )*
+    };
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Format(serde_json::Error),
+    TooNew { version: u32 },
+    Corrupt(String),
+    World(specs::error::WrongGeneration),
+    SlotsFull,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "Unable to access the save: {}", e),
+            SaveError::Format(e) => write!(f, "Unable to read the save: {}", e),
+            SaveError::TooNew { version } => write!(
+                f,
+                "The save is from a newer version of the game ({} > {})",
+                version, SAVE_VERSION
+            ),
+            SaveError::Corrupt(reason) => write!(f, "The save is damaged: {}", reason),
+            SaveError::World(e) => write!(f, "Unable to clear out the world: {}", e),
+            SaveError::SlotsFull => write!(
+                f,
+                "Every save slot is taken. Load or finish a run to free one up"
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(e: std::io::Error) -> Self {
+        SaveError::Io(e)
     }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> Self {
+        SaveError::Format(e)
+    }
+}
+
+impl From<specs::error::WrongGeneration> for SaveError {
+    fn from(e: specs::error::WrongGeneration) -> Self {
+        SaveError::World(e)
+    }
+}
+
+/// What goes on disk: a version header, a summary for the load screen, then every saved
+/// storage keyed by component name
+#[derive(Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub meta: SaveMeta,
+    pub components: JsonMap<String, Value>,
+}
+
+/// What the load screen shows about a save without loading it
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SaveMeta {
+    pub name: String,
+    pub schools: Vec<String>,
+    pub depth: i32,
+    pub turns: u32,
+    // Seconds since the Unix epoch, or 0 when nobody knows
+    pub timestamp: u64,
+}
+
+/// The slot the current run saves to, and whether it was loaded as practice. Practice runs
+/// leave their save in place when loaded and when they end.
+#[derive(PartialEq, Copy, Clone, Debug, Default)]
+pub struct ActiveSave {
+    pub slot: Option<usize>,
+    pub practice: bool,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum SlotStatus {
+    Empty,
+    Saved(SaveMeta),
+    Unreadable(String),
+}
+
+/// The slots as they were at the last look, so the load screen doesn't reread them every frame
+#[derive(Default)]
+pub struct SavedGames {
+    pub slots: Vec<SlotStatus>,
+}
+
+fn slot_path(slot: usize) -> PathBuf {
+    Path::new(SAVE_DIR).join(format!("slot{}.json", slot + 1))
+}
+
+pub fn register_saved_components(ecs: &mut World) {
+    saved_components!(register_individually, ecs);
+}
+
+pub fn save_game(ecs: &mut World) -> Result<(), SaveError> {
+    let save = serialize_world(ecs)?;
+    // Kept beside the save as well, where it's easy to pick up and attach to a bug report
+    super::super::replay::save_log(&ecs.fetch::<super::super::ActionLog>());
+
+    let slot = match ecs.fetch::<ActiveSave>().slot {
+        Some(slot) => slot,
+        None => free_slot(&list_saves().slots).ok_or(SaveError::SlotsFull)?,
+    };
+    fs::create_dir_all(SAVE_DIR)?;
+    // Write somewhere else first, so a failed save can't clobber the last good one
+    let path = slot_path(slot);
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, serde_json::to_string(&save)?)?;
+    fs::rename(&temp_path, &path)?;
+    ecs.write_resource::<ActiveSave>().slot = Some(slot);
+    Ok(())
+}
+
+/// The first empty slot. A taken slot holds another run, even if it can't be read, so it's
+/// never handed out.
+pub fn free_slot(slots: &[SlotStatus]) -> Option<usize> {
+    slots.iter().position(|slot| *slot == SlotStatus::Empty)
+}
+
+pub fn has_free_slot() -> bool {
+    free_slot(&list_saves().slots).is_some()
+}
+
+pub fn list_saves() -> SavedGames {
+    adopt_legacy_save();
+    let slots = (0..SAVE_SLOTS)
+        .map(|slot| {
+            let path = slot_path(slot);
+            if !path.exists() {
+                return SlotStatus::Empty;
+            }
+            let save = fs::read_to_string(&path)
+                .map_err(SaveError::from)
+                .and_then(|data| parse_save(&data));
+            match save {
+                Ok(save) => SlotStatus::Saved(save.meta),
+                Err(e) => SlotStatus::Unreadable(e.to_string()),
+            }
+        })
+        .collect();
+    SavedGames { slots }
+}
+
+// A save from before there were slots moves into the first empty one
+fn adopt_legacy_save() {
+    if !Path::new(LEGACY_SAVE_PATH).exists() {
+        return;
+    }
+    if let Some(slot) = (0..SAVE_SLOTS).find(|slot| !slot_path(*slot).exists()) {
+        let moved = fs::create_dir_all(SAVE_DIR)
+            .and_then(|_| fs::rename(LEGACY_SAVE_PATH, slot_path(slot)));
+        if let Err(e) = moved {
+            console::log(format!("WARNING - Unable to move the old save into a slot: {}", e));
+        }
+    }
+}
+
+fn current_meta(ecs: &World) -> SaveMeta {
+    let player_entity = *ecs.fetch::<Entity>();
+    let names = ecs.read_storage::<Name>();
+    let schools = ecs.read_storage::<MagicSchools>();
+    SaveMeta {
+        name: names
+            .get(player_entity)
+            .map_or(String::new(), |name| name.name.clone()),
+        schools: schools
+            .get(player_entity)
+            .map_or(Vec::new(), |schools| schools.names.clone()),
+        depth: ecs.fetch::<super::super::map::Map>().depth,
+        turns: ecs.fetch::<TurnCounter>().turns,
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs()),
+    }
+}
+
+pub fn serialize_world(ecs: &mut World) -> Result<SaveFile, SaveError> {
+    // Create helper
+    let mapcopy = ecs.get_mut::<super::super::map::Map>().unwrap().clone();
+    let log = (*ecs.fetch::<super::super::ActionLog>()).clone();
+    let turns = *ecs.fetch::<TurnCounter>();
+    let dungeon = (*ecs.fetch::<super::super::dungeon::MasterDungeonMap>()).clone();
+    let meta = current_meta(ecs);
+    let savehelper = ecs
+        .create_entity()
+        .with(SerializationHelper {
+            map: mapcopy,
+            log,
+            turns,
+            dungeon,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+
+    // Actually serialize
+    let components = serialize_components(ecs);
 
     // Clean up
-    ecs.delete_entity(savehelper).expect("Crash on cleanup");
+    ecs.delete_entity(savehelper)?;
+    Ok(SaveFile {
+        version: SAVE_VERSION,
+        meta,
+        components: components?,
+    })
+}
+
+fn serialize_components(ecs: &World) -> Result<JsonMap<String, Value>, SaveError> {
+    let mut components = JsonMap::new();
+    let data = (
+        ecs.entities(),
+        ecs.read_storage::<SimpleMarker<SerializeMe>>(),
+    );
+    saved_components!(serialize_individually, ecs, components, data);
+    Ok(components)
+}
+
+/// Serializes only the given entities, in the same form a save uses
+pub fn serialize_entities(
+    ecs: &World,
+    entities: &[Entity],
+) -> Result<JsonMap<String, Value>, SaveError> {
+    let ids: Vec<u64> = {
+        let markers = ecs.read_storage::<SimpleMarker<SerializeMe>>();
+        entities
+            .iter()
+            .filter_map(|e| markers.get(*e).map(|marker| marker.id()))
+            .collect()
+    };
+    let mut components = serialize_components(ecs)?;
+    for storage in components.values_mut() {
+        if let Value::Array(entries) = storage {
+            entries.retain(|entry| {
+                entry["marker"][0]
+                    .as_u64()
+                    .is_some_and(|id| ids.contains(&id))
+            });
+        }
+    }
+    Ok(components)
+}
+
+/// Adds serialized entities to the world as it is. Anything they refer to has to be either in
+/// the same batch or already in the world.
+pub fn deserialize_components(
+    ecs: &mut World,
+    mut components: JsonMap<String, Value>,
+) -> Result<(), SaveError> {
+    let mut d = (
+        &mut ecs.entities(),
+        &mut ecs.write_storage::<SimpleMarker<SerializeMe>>(),
+        &mut ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>(),
+    );
+    saved_components!(deserialize_individually, ecs, components, d);
+    Ok(())
 }
 
 pub fn does_save_exist() -> bool {
-    Path::new("./savegame.json").exists()
+    Path::new(LEGACY_SAVE_PATH).exists() || (0..SAVE_SLOTS).any(|slot| slot_path(slot).exists())
+}
+
+pub fn delete_save(slot: usize) -> Result<(), SaveError> {
+    let path = slot_path(slot);
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Roguelike runs take their save with them when they end. Practice runs leave it.
+pub fn delete_active_save(ecs: &World) -> Result<(), SaveError> {
+    let active = *ecs.fetch::<ActiveSave>();
+    match (active.slot, active.practice) {
+        (Some(slot), false) => delete_save(slot),
+        _ => Ok(()),
+    }
+}
+
+pub fn load_game(ecs: &mut World, slot: usize, practice: bool) -> Result<(), SaveError> {
+    let data = fs::read_to_string(slot_path(slot))?;
+    let save = parse_save(&data)?;
+    deserialize_world(ecs, save)?;
+    *ecs.write_resource::<ActiveSave>() = ActiveSave {
+        slot: Some(slot),
+        practice,
+    };
+    if !practice {
+        delete_save(slot)?;
+    }
+    Ok(())
+}
+
+/// Reads a save of any version and brings it up to date
+pub fn parse_save(data: &str) -> Result<SaveFile, SaveError> {
+    let mut values = serde_json::Deserializer::from_str(data)
+        .into_iter::<Value>()
+        .collect::<Result<Vec<Value>, _>>()?;
+    let mut save = match values.as_slice() {
+        [Value::Object(header)] if header.contains_key("version") => {
+            // A newer save needn't be laid out the way this build expects, so go by its version
+            let version = header["version"].as_u64().unwrap_or(0) as u32;
+            if version > SAVE_VERSION {
+                return Err(SaveError::TooNew { version });
+            }
+            serde_json::from_value(values.remove(0))?
+        }
+        // Saves from before the header was added are a bare run of storages
+        _ => {
+            let mut components = JsonMap::new();
+            components.insert("unversioned".to_string(), Value::Array(values));
+            SaveFile {
+                version: 0,
+                meta: SaveMeta::default(),
+                components,
+            }
+        }
+    };
+    while save.version < SAVE_VERSION {
+        MIGRATIONS[save.version as usize](&mut save)?;
+        save.version += 1;
+    }
+    Ok(save)
+}
+
+// Saves from before the version header wrote their storages back to back, in this order
+const FIRST_BUILD_ORDER: [&str; 30] = [
+    "Position",
+    "Renderable",
+    "Player",
+    "Viewshed",
+    "Monster",
+    "Name",
+    "BlocksTile",
+    "EntityStats",
+    "WantsToMelee",
+    "Item",
+    "Consumable",
+    "Ranged",
+    "InflictsDamage",
+    "AreaOfEffect",
+    "ProvidesHealing",
+    "InBackpack",
+    "WantsToPickupItem",
+    "WantsToUseItem",
+    "WantsToDropItem",
+    "Cursor",
+    "ParticleLifetime",
+    "SerializationHelper",
+    "WantsToCastSpell",
+    "Antagonistic",
+    "SingleActivation",
+    "ApplyTeleport",
+    "TeleportTo",
+    "SpawnParticleLine",
+    "SpawnParticleBurst",
+    "Hidden",
+];
+
+fn from_unversioned(save: &mut SaveFile) -> Result<(), SaveError> {
+    let storages = match save.components.remove("unversioned") {
+        Some(Value::Array(storages)) => storages,
+        _ => Vec::new(),
+    };
+    if storages.len() != FIRST_BUILD_ORDER.len() {
+        return Err(SaveError::Corrupt(
+            "it's from an unversioned build this one can't read".to_string(),
+        ));
+    }
+    save.components = FIRST_BUILD_ORDER
+        .iter()
+        .map(|name| name.to_string())
+        .zip(storages)
+        .collect();
+    fill_in_first_build(save)
+}
+
+// The first builds only ever had the one level, with the player against everything
+// Antagonistic. Everything added since gets its starting value: nobody has a level, XP or
+// initiative yet, monsters chase, and fighters roll the dice they'd get now.
+fn fill_in_first_build(save: &mut SaveFile) -> Result<(), SaveError> {
+    let components = &mut save.components;
+    let markers_of = |components: &JsonMap<String, Value>, name: &str| -> Vec<Value> {
+        stored_components(components, name)
+            .into_iter()
+            .map(|(marker, _)| marker.clone())
+            .collect()
+    };
+    let tagged = |markers: &[Value], component: Value| -> Vec<Value> {
+        markers
+            .iter()
+            .map(|marker| serde_json::json!({ "marker": marker, "components": [component] }))
+            .collect()
+    };
+    let players = markers_of(components, "Player");
+    let monsters = markers_of(components, "Monster");
+
+    give_stats(components, &players)?;
+    if let Some(Value::Array(damages)) = components.get_mut("InflictsDamage") {
+        for entry in damages.iter_mut() {
+            if let Some(Value::Object(damage)) = entry.pointer_mut("/components/0") {
+                if let Some(amount) = damage.get("damage").and_then(Value::as_i64) {
+                    let dice = Dice::flat(amount as i32);
+                    damage.insert("damage".to_string(), serde_json::to_value(dice)?);
+                }
+            }
+        }
+    }
+
+    let initiative = serde_json::json!({
+        "energy": 0,
+        "speed": super::initiative::ACTION_COST,
+    });
+    let actors: Vec<Value> = monsters.iter().chain(&players).cloned().collect();
+    components.insert(
+        "Initiative".to_string(),
+        Value::Array(tagged(&actors, initiative)),
+    );
+
+    let antagonists = markers_of(components, "Antagonistic");
+    let mut factions = tagged(&players, serde_json::json!({ "name": "Player" }));
+    factions.extend(tagged(
+        &antagonists,
+        serde_json::json!({ "name": crate::raws::DEFAULT_FACTION }),
+    ));
+    components.insert("Faction".to_string(), Value::Array(factions));
+    components.remove("Antagonistic");
+
+    let behaviours = {
+        let positions = stored_components(components, "Position");
+        monsters
+            .iter()
+            .map(|marker| {
+                // Every monster has a position, but a home at the origin beats refusing the save
+                let home = match positions.iter().find(|(at, _)| *at == marker) {
+                    Some((_, pos)) => serde_json::json!({ "x": pos["x"], "y": pos["y"] }),
+                    None => serde_json::json!({ "x": 0, "y": 0 }),
+                };
+                let behaviour = serde_json::json!({
+                    "kind": "Chaser",
+                    "state": "Idle",
+                    "last_seen": null,
+                    "home": home,
+                });
+                serde_json::json!({ "marker": marker, "components": [behaviour] })
+            })
+            .collect()
+    };
+    components.insert("Behaviour".to_string(), Value::Array(behaviours));
+
+    // Anything on the map that isn't the player belongs to the level
+    let members: Vec<Value> = markers_of(components, "Position")
+        .into_iter()
+        .filter(|marker| !players.contains(marker))
+        .collect();
+    components.insert(
+        "OnLevel".to_string(),
+        Value::Array(tagged(&members, serde_json::json!({}))),
+    );
+
+    // Spell casting intents named their spell differently, and don't outlast a turn anyway
+    components.insert("WantsToCastSpell".to_string(), Value::Array(Vec::new()));
+
+    save.meta = first_build_meta(&save.components)?;
+    // Nothing was recorded to replay or counted, so both start from the load
+    let log = crate::ActionLog::new(crate::RunSeed::new(0), [0, 0]);
+    add_to_helper(save, "log", serde_json::to_value(log)?);
+    add_to_helper(save, "turns", serde_json::to_value(TurnCounter::default())?);
+    let dungeon = serde_json::to_value(super::super::dungeon::MasterDungeonMap::default())?;
+    add_to_helper(save, "dungeon", dungeon);
+    Ok(())
+}
+
+// Everyone starts at level 1 with the damage dice they'd get now: the player their bare hands,
+// monsters whatever the raws give them, and anything else its old power
+fn give_stats(components: &mut JsonMap<String, Value>, players: &[Value]) -> Result<(), SaveError> {
+    let names: Vec<(Value, String)> = stored_components(components, "Name")
+        .into_iter()
+        .filter_map(|(marker, name)| Some((marker.clone(), name["name"].as_str()?.to_string())))
+        .collect();
+    let raws = crate::raws::RAWS.lock().unwrap();
+    let dice_for = |marker: &Value, power: i32| {
+        if players.contains(marker) {
+            return crate::spawner::PLAYER_DAMAGE;
+        }
+        names
+            .iter()
+            .find(|(named, _)| named == marker)
+            .and_then(|(_, name)| crate::raws::mob_damage(name, &raws))
+            .unwrap_or(Dice::flat(power))
+    };
+    if let Some(Value::Array(stats)) = components.get_mut("EntityStats") {
+        for entry in stats.iter_mut() {
+            let marker = entry["marker"].clone();
+            if let Some(Value::Object(stats)) = entry.pointer_mut("/components/0") {
+                let power = stats.get("power").and_then(Value::as_i64).unwrap_or(0) as i32;
+                stats.insert(
+                    "damage".to_string(),
+                    serde_json::to_value(dice_for(&marker, power))?,
+                );
+                stats.entry("level").or_insert(Value::from(1));
+                if let (true, Some(Value::Object(pools))) =
+                    (players.contains(&marker), stats.get_mut("pools"))
+                {
+                    let xp = Pool {
+                        max: experience_for_level(1),
+                        current: 0,
+                    };
+                    pools.insert("xp".to_string(), serde_json::to_value(xp)?);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// What the load screen shows, dug out of the storages. The first builds had no schools to list.
+fn first_build_meta(components: &JsonMap<String, Value>) -> Result<SaveMeta, SaveError> {
+    let player = stored_components(components, "Player")
+        .first()
+        .map(|(marker, _)| (*marker).clone());
+    let name = stored_components(components, "Name")
+        .into_iter()
+        .find(|(marker, _)| Some(*marker) == player.as_ref())
+        .and_then(|(_, name)| name["name"].as_str().map(str::to_string))
+        .unwrap_or_default();
+    let depth = stored_components(components, "SerializationHelper")
+        .first()
+        .and_then(|(_, helper)| helper["map"]["depth"].as_i64())
+        .ok_or_else(|| SaveError::Corrupt("there's no map in it".to_string()))?;
+    Ok(SaveMeta {
+        name,
+        depth: depth as i32,
+        ..SaveMeta::default()
+    })
 }
 
-pub fn delete_save() {
-    if Path::new("./savegame.json").exists() {
-        std::fs::remove_file("./savegame.json").expect("Unable to delete file");
+// A storage is a list of {"marker": [id], "components": [component or null]}
+fn stored_components<'a>(
+    components: &'a JsonMap<String, Value>,
+    name: &str,
+) -> Vec<(&'a Value, &'a Value)> {
+    components
+        .get(name)
+        .and_then(Value::as_array)
+        .map_or(Vec::new(), |entries| {
+            entries
+                .iter()
+                .filter_map(|entry| {
+                    let component = entry["components"].get(0).filter(|c| !c.is_null())?;
+                    Some((&entry["marker"], component))
+                })
+                .collect()
+        })
+}
+
+fn add_to_helper(save: &mut SaveFile, field: &str, value: Value) {
+    if let Some(Value::Array(entries)) = save.components.get_mut("SerializationHelper") {
+        for entry in entries.iter_mut() {
+            if let Some(Value::Object(helper)) =
+                entry.get_mut("components").and_then(|c| c.get_mut(0))
+            {
+                helper.insert(field.to_string(), value.clone());
+            }
+        }
     }
 }
 
-pub fn load_game(ecs: &mut World) {
+pub fn deserialize_world(ecs: &mut World, save: SaveFile) -> Result<(), SaveError> {
     {
         // Delete everything
         let mut to_delete = Vec::new();
@@ -114,56 +712,11 @@ pub fn load_game(ecs: &mut World) {
             to_delete.push(e);
         }
         for del in to_delete.iter() {
-            ecs.delete_entity(*del).expect("Deletion failed");
+            ecs.delete_entity(*del)?;
         }
     }
 
-    let data = fs::read_to_string("./savegame.json").unwrap();
-    let mut de = serde_json::Deserializer::from_str(&data);
-
-    {
-        let mut d = (
-            &mut ecs.entities(),
-            &mut ecs.write_storage::<SimpleMarker<SerializeMe>>(),
-            &mut ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>(),
-        );
-
-        deserialize_individually!(
-            ecs,
-            de,
-            d,
-            Position,
-            Renderable,
-            Player,
-            Viewshed,
-            Monster,
-            Name,
-            BlocksTile,
-            EntityStats,
-            WantsToMelee,
-            Item,
-            Consumable,
-            Ranged,
-            InflictsDamage,
-            AreaOfEffect,
-            ProvidesHealing,
-            InBackpack,
-            WantsToPickupItem,
-            WantsToUseItem,
-            WantsToDropItem,
-            Cursor,
-            ParticleLifetime,
-            SerializationHelper,
-            WantsToCastSpell,
-            Antagonistic,
-            SingleActivation,
-            ApplyTeleport,
-            TeleportTo,
-            SpawnParticleLine,
-            SpawnParticleBurst,
-            Hidden
-        );
-    }
+    deserialize_components(ecs, save.components)?;
 
     let mut deleteme: Option<Entity> = None;
     {
@@ -175,6 +728,13 @@ pub fn load_game(ecs: &mut World) {
             let mut worldmap = ecs.write_resource::<super::super::map::Map>();
             *worldmap = h.map.clone();
             worldmap.tile_content = vec![Vec::new(); worldmap.tile_count];
+            // The generator's state isn't saved, but the next input reseeds it anyway
+            *ecs.write_resource::<rltk::RandomNumberGenerator>() =
+                h.log.seed.input_rng(h.log.inputs.len());
+            *ecs.write_resource::<super::super::RunSeed>() = h.log.seed;
+            *ecs.write_resource::<super::super::ActionLog>() = h.log.clone();
+            *ecs.write_resource::<TurnCounter>() = h.turns;
+            *ecs.write_resource::<super::super::dungeon::MasterDungeonMap>() = h.dungeon.clone();
             deleteme = Some(e);
         }
         for (e, _p, pos) in (&entities, &player, &position).join() {
@@ -184,6 +744,202 @@ pub fn load_game(ecs: &mut World) {
             *player_resource = e;
         }
     }
-    ecs.delete_entity(deleteme.unwrap())
-        .expect("Unable to delete helper");
+    match deleteme {
+        Some(helper) => {
+            ecs.delete_entity(helper)?;
+            reserve_stored_markers(ecs)
+        }
+        None => Err(SaveError::Corrupt("there's no map in it".to_string())),
+    }
+}
+
+// The allocator only knows the markers it has loaded, so entities spawned from here on could
+// take one that belongs to a stored level. Claiming the highest stored marker for a throwaway
+// entity moves the allocator past all of them.
+fn reserve_stored_markers(ecs: &mut World) -> Result<(), SaveError> {
+    let highest = ecs
+        .fetch::<super::super::dungeon::MasterDungeonMap>()
+        .max_marker_id();
+    if let Some(id) = highest {
+        let placeholder = ecs.create_entity().build();
+        ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>()
+            .allocate(placeholder, Some(id));
+        ecs.delete_entity(placeholder)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::Simulation;
+
+    fn count<T: Component>(ecs: &World) -> usize {
+        ecs.read_storage::<T>().join().count()
+    }
+
+    // Entities that belong to the level and are lying about on it
+    fn on_floor(ecs: &World) -> usize {
+        let on_level = ecs.read_storage::<OnLevel>();
+        let positions = ecs.read_storage::<Position>();
+        (&on_level, &positions).join().count()
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let mut sim = Simulation::new(7, ["Pyromancy", "Evocation"]);
+        let pos = sim.player_pos();
+        sim.spawn("Mana Potion", pos.x, pos.y);
+        let spells = count::<Spell>(&sim.state.ecs);
+        let potions = count::<ProvidesMana>(&sim.state.ecs);
+        let costs = count::<CostsMana>(&sim.state.ecs);
+        assert!(spells > 0 && potions > 0 && costs > 0);
+
+        let data = serde_json::to_string(&serialize_world(sim.ecs()).unwrap()).unwrap();
+        let mut loaded = Simulation::new(8, ["Cryomancy", "Translocation"]);
+        deserialize_world(loaded.ecs(), parse_save(&data).unwrap()).unwrap();
+
+        assert_eq!(count::<Spell>(&loaded.state.ecs), spells);
+        assert_eq!(count::<ProvidesMana>(&loaded.state.ecs), potions);
+        assert_eq!(count::<CostsMana>(&loaded.state.ecs), costs);
+        assert_eq!(loaded.player_pos(), pos);
+        assert_eq!(loaded.state.ecs.fetch::<crate::RunSeed>().seed, 7);
+        let meta = parse_save(&data).unwrap().meta;
+        assert_eq!(meta.schools, vec!["Pyromancy", "Evocation"]);
+        assert_eq!(meta.depth, 1);
+    }
+
+    #[test]
+    fn test_stored_levels_are_saved() {
+        let mut sim = Simulation::new(14, ["Pyromancy", "Cryomancy"]);
+        let monsters = count::<Monster>(&sim.state.ecs);
+        sim.stand_on(crate::TileType::DownStairs);
+        sim.input(crate::PlayerInput::Act(crate::PlayerAction::Descend));
+
+        let data = serde_json::to_string(&serialize_world(sim.ecs()).unwrap()).unwrap();
+        let mut loaded = Simulation::new(15, ["Pyromancy", "Cryomancy"]);
+        deserialize_world(loaded.ecs(), parse_save(&data).unwrap()).unwrap();
+        // Nothing spawned after the load may take a marker the stored level still holds
+        let spawned = loaded.spawn("Goblin", 1, 1);
+        let stored = loaded
+            .state
+            .ecs
+            .fetch::<crate::dungeon::MasterDungeonMap>()
+            .max_marker_id()
+            .unwrap();
+        let markers = loaded.state.ecs.read_storage::<SimpleMarker<SerializeMe>>();
+        assert!(markers.get(spawned).unwrap().id() > stored);
+        drop(markers);
+
+        loaded.input(crate::PlayerInput::Act(crate::PlayerAction::Ascend));
+        assert_eq!(loaded.state.ecs.fetch::<crate::Map>().depth, 1);
+        assert_eq!(count::<Monster>(&loaded.state.ecs), monsters);
+    }
+
+    #[test]
+    fn test_first_build_save_migrates() {
+        let mut sim = Simulation::new(9, ["Pyromancy", "Cryomancy"]);
+        let pos = sim.player_pos();
+        sim.spawn("Troll", pos.x + 1, pos.y);
+        let save = serialize_world(sim.ecs()).unwrap();
+
+        // Take out what the first builds didn't have. Every monster was Antagonistic back then.
+        let mut first = serde_json::to_value(&save.components).unwrap();
+        let storages = first.as_object_mut().unwrap();
+        let antagonists = stored_components(storages, "Monster")
+            .into_iter()
+            .map(|(marker, _)| serde_json::json!({ "marker": marker, "components": [{}] }))
+            .collect();
+        storages.insert("Antagonistic".to_string(), Value::Array(antagonists));
+        for entry in storages["EntityStats"].as_array_mut().unwrap() {
+            if let Some(Value::Object(stats)) = entry.pointer_mut("/components/0") {
+                stats.remove("level");
+                stats.remove("damage");
+                stats["pools"].as_object_mut().unwrap().remove("xp");
+            }
+        }
+        for entry in storages["InflictsDamage"].as_array_mut().unwrap() {
+            if let Some(damage) = entry.pointer_mut("/components/0/damage") {
+                *damage = Value::from(5);
+            }
+        }
+        for entry in storages["SerializationHelper"].as_array_mut().unwrap() {
+            if let Some(Value::Object(helper)) = entry.pointer_mut("/components/0") {
+                helper.retain(|field, _| field == "map");
+            }
+        }
+        // They wrote their storages back to back, with no header
+        let data = FIRST_BUILD_ORDER
+            .iter()
+            .map(|name| storages[*name].to_string())
+            .collect::<String>();
+
+        let migrated = parse_save(&data).unwrap();
+        assert_eq!(migrated.version, SAVE_VERSION);
+        assert_eq!(migrated.meta.name, save.meta.name);
+        assert_eq!(migrated.meta.depth, save.meta.depth);
+        let mut loaded = Simulation::new(10, ["Pyromancy", "Cryomancy"]);
+        deserialize_world(loaded.ecs(), migrated).unwrap();
+        assert_eq!(loaded.player_pos(), pos);
+        assert_eq!(on_floor(&loaded.state.ecs), on_floor(&sim.state.ecs));
+        assert_eq!(loaded.state.ecs.fetch::<TurnCounter>().turns, 0);
+
+        let monsters = count::<Monster>(&sim.state.ecs);
+        assert_eq!(count::<Behaviour>(&loaded.state.ecs), monsters);
+        assert_eq!(count::<Initiative>(&loaded.state.ecs), monsters + 1);
+        let player = loaded.player();
+        {
+            let ecs = &loaded.state.ecs;
+            let factions = ecs.read_storage::<Faction>();
+            assert_eq!(factions.get(player).unwrap().name, "Player");
+            let monsters = ecs.read_storage::<Monster>();
+            assert!((&factions, &monsters)
+                .join()
+                .all(|(faction, _)| faction.name == crate::raws::DEFAULT_FACTION));
+
+            let stats = ecs.read_storage::<EntityStats>();
+            let player_stats = stats.get(player).unwrap();
+            assert_eq!(player_stats.level, 1);
+            assert_eq!(player_stats.get("xp").0, 0);
+            assert_eq!(player_stats.damage, crate::spawner::PLAYER_DAMAGE);
+            let names = ecs.read_storage::<Name>();
+            let (troll, _) = (&stats, &names)
+                .join()
+                .find(|(_, name)| name.name == "Troll")
+                .unwrap();
+            assert_eq!(troll.damage, Dice::parse("2d6+2").unwrap());
+            let damages = ecs.read_storage::<InflictsDamage>();
+            assert!(damages.join().count() > 0);
+            assert!(damages.join().all(|damage| damage.damage == Dice::flat(5)));
+        }
+        // Everyone gets a turn, and the player's comes back around
+        let runstate = loaded.input(crate::PlayerInput::Act(crate::PlayerAction::Wait));
+        assert!(runstate == crate::RunState::AwaitingInput);
+
+        assert!(matches!(parse_save("[] []"), Err(SaveError::Corrupt(_))));
+    }
+
+    #[test]
+    fn test_free_slot() {
+        let saved = |timestamp| {
+            SlotStatus::Saved(SaveMeta {
+                timestamp,
+                ..SaveMeta::default()
+            })
+        };
+        assert_eq!(
+            free_slot(&[saved(5), SlotStatus::Empty, SlotStatus::Empty]),
+            Some(1)
+        );
+        // Full slots are left alone, however old their saves are
+        assert_eq!(free_slot(&[saved(5), saved(3), saved(9)]), None);
+        let unreadable = SlotStatus::Unreadable(String::new());
+        assert_eq!(free_slot(&[saved(5), saved(3), unreadable]), None);
+    }
+
+    #[test]
+    fn test_newer_save_is_refused() {
+        let data = format!("{{\"version\":{},\"components\":{{}}}}", SAVE_VERSION + 1);
+        assert!(matches!(parse_save(&data), Err(SaveError::TooNew { .. })));
+    }
 }
@@ -1,40 +1,164 @@
-use crate::{gamelog::GameLog, InflictsDamage, Name, Ranged, SerializeMe, Spell, WantsToCastSpell};
+use crate::{
+    camera, effects::*, gamelog::GameLog, map::Map, AreaOfEffect, Cursor, Name, Ranged,
+    RunState, WantsToCastSpell,
+};
 
-use specs::saveload::MarkedBuilder;
-use specs::{prelude::*, saveload::SimpleMarker};
+use rltk::Point;
+use specs::prelude::*;
 // Spells look a lot like items, key difference is they are not consumable, and don't interact with
 // the inventory
 
 pub struct SpellCast {}
 
 impl<'a> System<'a> for SpellCast {
+    #[allow(clippy::type_complexity)]
     type SystemData = (
         ReadExpect<'a, Entity>,
         WriteExpect<'a, GameLog>,
+        WriteExpect<'a, EffectQueue>,
+        Entities<'a>,
         WriteStorage<'a, WantsToCastSpell>,
+        ReadExpect<'a, Map>,
+        ReadStorage<'a, AreaOfEffect>,
+        ReadStorage<'a, Name>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        // for now, if its the player casting, we just log something
-        let (player_entity, mut gamelog, mut wants_spellcast) = data;
+        let (
+            player_entity,
+            mut gamelog,
+            mut effects,
+            entities,
+            mut wants_spellcast,
+            map,
+            aoe,
+            names,
+        ) = data;
 
-        for cast in wants_spellcast.join() {
-            if cast.source == *player_entity {
-                gamelog.entries.push("You cast a spell".to_string());
+        for (entity, cast) in (&entities, &wants_spellcast).join() {
+            if let Some(name) = names.get(cast.spell) {
+                if entity == *player_entity {
+                    gamelog.entries.push(format!("You cast {}", name.name));
+                } else if let Some(caster) = names.get(entity) {
+                    gamelog
+                        .entries
+                        .push(format!("{} casts {}", caster.name, name.name));
+                }
             }
+            effects.add(
+                Some(entity),
+                EffectType::SpellUse { spell: cast.spell },
+                match cast.target {
+                    None => Targets::Single { target: entity },
+                    Some(target) => {
+                        if let Some(aoe) = aoe.get(cast.spell) {
+                            Targets::Tiles {
+                                tiles: aoe_tiles(&map, target, aoe.radius),
+                            }
+                        } else {
+                            Targets::Tile {
+                                tile_idx: map.xy_idx(target.x, target.y) as i32,
+                            }
+                        }
+                    }
+                },
+            );
         }
         wants_spellcast.clear();
     }
 }
 
-pub fn fireball_spell(ecs: &mut World, hkey: String) {
-    ecs.create_entity()
-        .with(Name {
-            name: "Fireball Spell".to_string(),
-        })
-        .with(Spell { hotkey: hkey })
-        .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 8 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+pub fn cast_spell(ecs: &mut World, spell: Entity) -> RunState {
+    let is_aoe = ecs.read_storage::<AreaOfEffect>();
+    let radius = match is_aoe.get(spell) {
+        Some(is_spell_aoe) => is_spell_aoe.radius,
+        None => 0,
+    };
+    if let Some(ranged) = ecs.read_storage::<Ranged>().get(spell) {
+        //reset cursor position
+        let player_pos = ecs.fetch::<Point>();
+        let mut cursor = ecs.fetch_mut::<Cursor>();
+        cursor.point = camera::tile_to_screen(ecs, *player_pos);
+        return RunState::ShowTargeting {
+            range: ranged.range,
+            item: spell,
+            radius,
+            throwing: false,
+        };
+    }
+    let mut intent = ecs.write_storage::<WantsToCastSpell>();
+    intent
+        .insert(
+            *ecs.fetch::<Entity>(),
+            WantsToCastSpell {
+                spell,
+                target: None,
+            },
+        )
+        .expect("Unable to insert intent");
+    RunState::PlayerTurn
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sim::Simulation;
+    use crate::{EntityStats, PlayerAction, PlayerInput, RunState, Spell};
+    use specs::prelude::*;
+
+    #[test]
+    fn test_targeted_spell() {
+        let mut sim = Simulation::quiet(["Evocation", "Pyromancy"]);
+        let player = sim.player();
+        let pos = sim.player_pos();
+        let goblin = sim.spawn("Goblin", pos.x + 2, pos.y);
+        let mana = sim
+            .ecs()
+            .read_storage::<EntityStats>()
+            .get(player)
+            .unwrap()
+            .get("mana")
+            .0;
+
+        let index = sim.spell_hotkey("Magic Missile");
+        sim.input(PlayerInput::Act(PlayerAction::Hotkey { index }));
+        assert!(sim.input(PlayerInput::CancelTarget) == RunState::AwaitingInput);
+        let runstate = sim.input(PlayerInput::Act(PlayerAction::Hotkey { index }));
+        assert!(matches!(runstate, RunState::ShowTargeting { .. }));
+
+        sim.input(PlayerInput::Target {
+            x: pos.x + 2,
+            y: pos.y,
+        });
+        // Spells roll to hit like anything else, and Magic Missile does 1d4+3 when it lands
+        let log = sim.log();
+        let hit = log
+            .iter()
+            .find(|entry| entry.starts_with("Player's Magic Missile hits Goblin"));
+        assert!(hit.unwrap().contains(" vs "));
+        assert!((1..=4).contains(&sim.hit_points(goblin)));
+        let stats = sim.ecs().read_storage::<EntityStats>();
+        assert_eq!(stats.get(player).unwrap().get("mana").0, mana - 1);
+    }
+
+    #[test]
+    fn test_monster_casts_spells() {
+        let mut sim = Simulation::quiet(["Pyromancy", "Evocation"]);
+        let pos = sim.player_pos();
+        let shaman = sim.spawn("Kobold Shaman", pos.x + 4, pos.y);
+        let player = sim.player();
+        let before = sim.hit_points(player);
+
+        sim.input(PlayerInput::Act(PlayerAction::Wait));
+        assert!(sim
+            .log()
+            .iter()
+            .any(|entry| entry == "Kobold Shaman casts Firebolt"));
+        assert!(sim.hit_points(player) < before);
+        let stats = sim.ecs().read_storage::<EntityStats>();
+        let (mana, max_mana) = stats.get(shaman).unwrap().get("mana");
+        assert!(mana < max_mana);
+        drop(stats);
+        // The player doesn't pick up what a monster knows
+        assert!(sim.ecs().read_storage::<Spell>().join().count() == 2);
+    }
 }
@@ -0,0 +1,92 @@
+use crate::effects::{EffectQueue, EffectType, Targets};
+use crate::{
+    GameLog, InBackpack, KnownSpell, MyTurn, Name, RunState, StatusEffects, StatusType,
+};
+use specs::prelude::*;
+
+// Statuses tick at the end of each of their bearer's turns. The player's turn ends in PlayerTurn,
+// everyone else acts while Ticking
+pub struct StatusTick {}
+
+impl<'a> System<'a> for StatusTick {
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, EffectQueue>,
+        Entities<'a>,
+        WriteStorage<'a, StatusEffects>,
+        ReadStorage<'a, MyTurn>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, KnownSpell>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            runstate,
+            mut gamelog,
+            mut effect_queue,
+            entities,
+            mut status_effects,
+            turns,
+            names,
+            backpack,
+            known_spells,
+        ) = data;
+
+        let ticking = *runstate == RunState::Ticking;
+        if !ticking && *runstate != RunState::PlayerTurn {
+            return;
+        }
+
+        for (entity, effects, _turn) in (&entities, &mut status_effects, &turns).join() {
+            if ticking && entity == *player_entity {
+                continue;
+            }
+
+            for status in effects.statuses.iter() {
+                let damage = status.damage();
+                if damage > 0 {
+                    effect_queue.add(
+                        None,
+                        EffectType::Damage { amount: damage },
+                        Targets::Single { target: entity },
+                    );
+                }
+            }
+
+            let mut dismissed = false;
+            effects.statuses.retain_mut(|status| {
+                let active = status.tick();
+                if !active && entity == *player_entity {
+                    gamelog
+                        .entries
+                        .push(format!("You are no longer afflicted with {}", status.typ.name()));
+                }
+                dismissed |= !active && status.typ == StatusType::Summoned;
+                active
+            });
+
+            // A summon's time is up: it goes back where it came from, along with what it had
+            if dismissed {
+                if let Some(name) = names.get(entity) {
+                    gamelog.entries.push(format!("{} fades away.", name.name));
+                }
+                let carried = (&entities, &backpack)
+                    .join()
+                    .filter(|(_, pack)| pack.owner == entity)
+                    .map(|(item, _)| item);
+                let known = (&entities, &known_spells)
+                    .join()
+                    .filter(|(_, known)| known.owner == entity)
+                    .map(|(spell, _)| spell);
+                for belonging in carried.chain(known).collect::<Vec<_>>() {
+                    entities.delete(belonging).expect("Unable to delete");
+                }
+                entities.delete(entity).expect("Unable to delete");
+            }
+        }
+    }
+}